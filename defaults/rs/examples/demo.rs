@@ -0,0 +1,46 @@
+//
+//
+use default_session_tokens::{default_token_maker, SessionTokenSets, SessionTokenTraits, Token};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+fn main() {
+
+    let st : Token = default_token_maker(Some("user+"));
+    let tt : Token = default_token_maker(None);
+    let prfx_tt : Token = default_token_maker(Some("media+"));
+
+    println!("{:#?}",st);
+    println!("{:#?}",tt);
+    println!("{:#?}",prfx_tt);
+
+    //
+
+    let mut sts : SessionTokenSets = SessionTokenSets::new();
+
+    match tt {
+        Token::TransitionToken(trans) => {
+            sts.session_carries.insert(trans);
+        },
+        Token::SessionToken(sess) => {
+            println!("{:#?}",sess);
+        }
+    }
+
+    match st {
+        Token::TransitionToken(trans) => {
+            sts.session_carries.insert(trans);
+        },
+        Token::SessionToken(sess) => {
+            sts.session_bounded.insert(sess.clone());
+            println!("{:#?}",sess);
+        }
+    }
+
+
+
+    //
+    println!("IDs Listed!");
+}
@@ -0,0 +1,191 @@
+//
+//
+use data_encoding::BASE64URL_NOPAD;
+use derive_builder::Builder;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::clock::{now_millis, Millis};
+use crate::errors::{SessionTokenError, SessionTokenResult};
+use crate::scopes::ScopeSet;
+use crate::tokens::{SessionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+type HmacSha256 = Hmac<Sha256>;
+
+
+/**
+ * A condition on the use of a caveat token. Every caveat in the chain has to hold.
+ * Several `Scopes` caveats narrow one another: a scope is allowed only if each of them lists it.
+ * `Owner` and `Session` carry a digest keyed by the root key, not the owner or session itself,
+ * so that reading a caveat token tells nothing a holder could use in place of it.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Caveat {
+    ExpiresAt(Millis),
+    Scopes(ScopeSet),
+    Owner(String),
+    Session(String),
+}
+
+
+impl Caveat {
+    //
+    pub fn owner(root_key : &[u8], owner : &Ucwid) -> Caveat {
+        Caveat::Owner(opaque_id(root_key, "owner", owner))
+    }
+
+    pub fn session(root_key : &[u8], session_token : &SessionToken) -> Caveat {
+        Caveat::Session(opaque_id(root_key, "session", session_token))
+    }
+}
+
+
+/**
+ * What the verifier knows about the use being made of a caveat token.
+ * Owner and session caveats fail when the verifier does not know the owner or session.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Builder)]
+pub struct CaveatContext {
+    #[builder(default = "now_millis()")]
+    pub _now : Millis,
+    #[builder(default = "None")]
+    pub _scope : Option<String>,
+    #[builder(default = "None")]
+    pub _owner : Option<Ucwid>,
+    #[builder(default = "None")]
+    pub _session : Option<SessionToken>,
+}
+
+
+/**
+ * A macaroon style token: an identifier followed by a chain of caveats, each folded into an HMAC.
+ * The first link is keyed by the root key; each caveat is signed with the link before it.
+ * So, any holder may add a caveat (restricting the token) but none may take one away,
+ * and any service with the root key may verify the token without looking anything up.
+ * The identifier is opaque (see `opaque_id`); only the minter maps it back to the token it stands for.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct CaveatToken {
+    pub _id : String,
+    pub _caveats : Vec<Caveat>,
+    _signature : Vec<u8>,
+}
+
+
+impl CaveatToken {
+    //
+    pub fn mint(root_key : &[u8], id : &str) -> CaveatToken {
+        CaveatToken { _id : id.to_string(), _caveats : vec![], _signature : chain_link(root_key, id.as_bytes()) }
+    }
+
+    // restrict
+    // adds a caveat -- needs no key, only the token
+
+    pub fn restrict(mut self, caveat : Caveat) -> SessionTokenResult<CaveatToken> {
+        let caveat_bytes = serde_json::to_vec(&caveat)?;
+        self._signature = chain_link(&self._signature, &caveat_bytes);
+        self._caveats.push(caveat);
+        Ok(self)
+    }
+
+    pub fn encode(&self) -> SessionTokenResult<String> {
+        Ok(BASE64URL_NOPAD.encode(&serde_json::to_vec(self)?))
+    }
+
+    pub fn decode(encoded : &str) -> SessionTokenResult<CaveatToken> {
+        let forged = || SessionTokenError::Forged(encoded.to_string());
+        let bytes = BASE64URL_NOPAD.decode(encoded.as_bytes()).map_err(|_| forged())?;
+        serde_json::from_slice(&bytes).map_err(|_| forged())
+    }
+
+    // verify
+    // recomputes the chain from the root key, then checks each caveat against the context
+
+    pub fn verify(&self, root_key : &[u8], context : &CaveatContext) -> SessionTokenResult<()> {
+        let mut signature = chain_link(root_key, self._id.as_bytes());
+        for caveat in &self._caveats {
+            signature = chain_link(&signature, &serde_json::to_vec(caveat)?);
+        }
+        let mut mac = HmacSha256::new_from_slice(root_key).expect("HMAC takes keys of any length");
+        mac.update(&signature);
+        let expected = mac.finalize().into_bytes();
+        let mut mac = HmacSha256::new_from_slice(root_key).expect("HMAC takes keys of any length");
+        mac.update(&self._signature);
+        if mac.verify_slice(&expected).is_err() {           // compared through a second HMAC so the comparison is constant time
+            return Err(SessionTokenError::Forged(self._id.to_string()))
+        }
+        for caveat in &self._caveats {
+            let holds = match caveat {
+                Caveat::ExpiresAt(at) => {
+                    if context._now >= *at {
+                        return Err(SessionTokenError::Expired(self._id.to_string()))
+                    }
+                    true
+                }
+                Caveat::Scopes(scopes) => context._scope.as_ref().is_some_and(|scope| scopes.contains(scope)),
+                Caveat::Owner(digest) => context._owner.as_ref().is_some_and(|owner| opaque_id(root_key, "owner", owner) == *digest),
+                Caveat::Session(digest) => context._session.as_ref().is_some_and(|session| opaque_id(root_key, "session", session) == *digest),
+            };
+            if !holds {
+                return Err(SessionTokenError::CaveatUnmet(self._id.to_string()))
+            }
+        }
+        Ok(())
+    }
+}
+
+
+// opaque_id
+// a keyed digest standing in for a token, owner or session -- the same for the same key, kind and text,
+// but nothing about the text can be learned from it without the key
+
+pub fn opaque_id(key : &[u8], kind : &str, text : &str) -> String {
+    BASE64URL_NOPAD.encode(&chain_link(key, format!("{}.{}", kind, text).as_bytes()))
+}
+
+
+fn chain_link(key : &[u8], data : &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scopes::scope_set;
+
+    #[test]
+    fn holders_may_restrict_but_not_loosen() {
+        let root_key = b"root key";
+        let token = CaveatToken::mint(root_key, "media+abc")
+                        .restrict(Caveat::Scopes(scope_set(&["media:read", "media:write"]))).unwrap()
+                        .restrict(Caveat::ExpiresAt(2000)).unwrap();
+        let restricted = CaveatToken::decode(&token.encode().unwrap()).unwrap().restrict(Caveat::Scopes(scope_set(&["media:read"]))).unwrap();
+        let context = |scope : &str| CaveatContextBuilder::default()._now(1000)._scope(Some(scope.to_string())).build().unwrap();
+        //
+        assert!(token.verify(root_key, &context("media:write")).is_ok());
+        assert_eq!(restricted.verify(root_key, &context("media:write")), Err(SessionTokenError::CaveatUnmet("media+abc".to_string())));
+        assert!(restricted.verify(root_key, &context("media:read")).is_ok());
+        //
+        let mut loosened = restricted.clone();
+        loosened._caveats.pop();
+        assert_eq!(loosened.verify(root_key, &context("media:write")), Err(SessionTokenError::Forged("media+abc".to_string())));
+        assert_eq!(token.verify(b"other key", &context("media:write")), Err(SessionTokenError::Forged("media+abc".to_string())));
+        let later = CaveatContextBuilder::default()._now(2000)._scope(Some("media:read".to_string())).build().unwrap();
+        assert_eq!(token.verify(root_key, &later), Err(SessionTokenError::Expired("media+abc".to_string())));
+    }
+}
@@ -0,0 +1,89 @@
+//
+//
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * Instants are kept as milliseconds since the UNIX epoch so that timing records
+ * may be shared through the DB with other processes.
+ */
+pub type Millis = u64;
+
+pub fn now_millis() -> Millis {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as Millis,
+        _ => 0
+    }
+}
+
+
+/**
+ * The source of the current time for LocalSessionTokens.
+ * Applications may supply their own, e.g. to share a time source between processes.
+ */
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Millis;
+}
+
+#[allow(non_camel_case_types)]
+pub type clock_box = Box<dyn Clock>;
+
+
+// ---- ----
+
+/**
+ * The default clock -- reads the system time.
+ */
+#[derive(Clone)]
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Millis {
+        now_millis()
+    }
+}
+
+
+// ---- ----
+
+/**
+ * A clock that only moves when it is told to.
+ * Clones share the same time, so a test may keep one clone and hand another to LocalSessionTokens,
+ * then jump ahead with `advance` instead of sleeping.
+ */
+#[derive(Clone)]
+pub struct MockClock {
+    _now : Arc<AtomicU64>,
+}
+
+impl MockClock {
+    //
+    pub fn new(start : Millis) -> MockClock {
+        MockClock { _now : Arc::new(AtomicU64::new(start)) }
+    }
+
+    pub fn advance(&self, by : Millis) {
+        self._now.fetch_add(by, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, at : Millis) {
+        self._now.store(at, Ordering::SeqCst);
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new(now_millis())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Millis {
+        self._now.load(Ordering::SeqCst)
+    }
+}
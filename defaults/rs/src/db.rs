@@ -0,0 +1,70 @@
+//
+//
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::tokens::{Hash, SessionToken, TransitionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * The failures a DB implementation may report.
+ * A missing key is not a failure; lookups return `Ok(None)` in that case.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+pub enum DBError {
+    Unavailable(String),        // the store could not be reached
+    Failed(String),             // the store was reached but the operation did not complete
+}
+
+pub type DBResult<T> = Result<T,DBError>;
+
+
+impl fmt::Display for DBError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DBError::Unavailable(msg) => write!(f, "db unavailable: {}", msg),
+            DBError::Failed(msg) => write!(f, "db operation failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DBError {}
+
+
+/**
+ * The outcome of taking one use of a token in the DB.
+ * `Refused` covers both a token with no uses left and a token that is no longer stored.
+ */
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+pub enum TokenUse {
+    Unlimited,          // the token has no use count
+    Taken(u32),         // a use was taken -- this many are left
+    Refused,
+}
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * Applications using the TokenTables traits will need to supply a DB object that provides these methods.
+ * The session DB stores a hash identifying the session, while the token DB stores actual values.
+ * Every operation is async and returns owned values, so that remote stores may be used.
+ */
+#[async_trait]
+pub trait DB: Sync + Send {
+    async fn set_session_key_value(&self, session_token : & SessionToken, ownership_key : Ucwid ) -> DBResult<Hash>;
+    async fn del_session_key_value(&self, session_token : & SessionToken ) -> DBResult<bool>;
+    async fn set_key_value(&self, token : & TransitionToken, value : &str )  -> DBResult<()>;
+    async fn get_key_value(&self, token : & TransitionToken )  -> DBResult<Option<String>>;
+    async fn del_key_value(&self, token : & TransitionToken )  -> DBResult<()>;
+    async fn check_hash(&self, hh_unidentified : &str, ownership_key : &Ucwid )  -> DBResult<bool>;
+    async fn set_use_count(&self, token : & TransitionToken, uses : u32 )  -> DBResult<()>;
+    async fn take_use(&self, token : & TransitionToken )  -> DBResult<TokenUse>;     // must be atomic across processes
+}
@@ -0,0 +1,82 @@
+//
+//
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::clock::Millis;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * Keys ordered by the instant at which they run out, soonest first.
+ * A key is scheduled again each time its deadline changes, and the older entries are left in the queue.
+ * So, an entry taken from the queue is only good if the deadline it carries is still the deadline of its record.
+ * This keeps every change O(log n) and lets a reaper pass touch only the entries that have come due.
+ */
+pub struct DeadlineQueue<K : Ord> {
+    _heap : BinaryHeap<Reverse<(Millis,K)>>,
+}
+
+
+impl<K : Ord> DeadlineQueue<K> {
+    //
+    pub fn new() -> DeadlineQueue<K> {
+        DeadlineQueue { _heap : BinaryHeap::new() }
+    }
+
+    pub fn schedule(&mut self, key : K, at : Millis) {
+        self._heap.push(Reverse((at,key)));
+    }
+
+    /// Takes out every entry due at or before `now`, soonest first.
+    pub fn due(&mut self, now : Millis) -> Vec<(Millis,K)> {
+        let mut v = Vec::<(Millis,K)>::new();
+        while let Some(Reverse((at, _))) = self._heap.peek() {
+            if *at > now {
+                break
+            }
+            if let Some(Reverse(entry)) = self._heap.pop() {
+                v.push(entry);
+            }
+        }
+        v
+    }
+
+    pub fn len(&self) -> usize {
+        self._heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._heap.is_empty()
+    }
+
+    /// Drops the entries that `keep` says are out of date.
+    pub fn retain<F>(&mut self, keep : F) where F: Fn(&K, Millis) -> bool {
+        self._heap.retain(|Reverse((at, key))| keep(key, *at));
+    }
+}
+
+impl<K : Ord> Default for DeadlineQueue<K> {
+    fn default() -> Self {
+        DeadlineQueue::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_entries_come_out_soonest_first() {
+        let mut dq = DeadlineQueue::<String>::new();
+        dq.schedule("b".to_string(), 20);
+        dq.schedule("a".to_string(), 10);
+        dq.schedule("c".to_string(), 30);
+        assert_eq!(dq.due(25), vec![(10, "a".to_string()), (20, "b".to_string())]);
+        assert_eq!(dq.len(), 1);
+        assert!(dq.due(29).is_empty());
+    }
+}
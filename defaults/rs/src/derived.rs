@@ -0,0 +1,26 @@
+//
+//
+use derive_builder::Builder;
+
+use crate::scopes::ScopeSet;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * What a derived token gives up relative to its parent.
+ * A derived token has the parent's owner, session and value. Its scopes are the parent's, or the subset given here,
+ * and it never outlives the parent: a timeout longer than the parent's time left is cut down to it.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(Builder)]
+pub struct TokenRestrictions {
+    #[builder(default = "None")]
+    pub _scopes : Option<ScopeSet>,
+    #[builder(default = "None")]
+    pub _timeout : Option<i32>,
+    #[builder(default = "None")]
+    pub _prefix : Option<String>,       // for the child token -- the creator's default when None
+}
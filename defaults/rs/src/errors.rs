@@ -0,0 +1,129 @@
+//
+//
+use std::fmt;
+
+use crate::caveats::CaveatContextBuilderError;
+use crate::db::DBError;
+use crate::derived::TokenRestrictionsBuilderError;
+use crate::prefixes::TokenPolicyBuilderError;
+use crate::timing::{SessionTimingInfoBuilderError, TokenTimingInfoBuilderError};
+use crate::tokens::{SessionToken, TransitionToken, Ucwid};
+use crate::transfer::TransferableTokenInfoBuilderError;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * The reasons an operation on the token tables may not take place.
+ * Each variant carries the key (session, token, or owner) that the operation was attempted with.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+pub enum SessionTokenError {
+    UnknownSession(SessionToken),
+    UnknownToken(TransitionToken),
+    UnknownOwner(Ucwid),
+    TooManySessions(Ucwid),
+    NotTransferable(TransitionToken),
+    NotOwner(TransitionToken),
+    Unauthorized(TransitionToken),
+    NotActive(SessionToken),
+    NotProven(SessionToken),
+    Expired(TransitionToken),
+    NotYetActive(TransitionToken),
+    Forged(TransitionToken),
+    Replayed(TransitionToken),
+    UsedUp(TransitionToken),
+    LimitedUse(TransitionToken),
+    OutOfScope(TransitionToken),
+    CaveatUnmet(TransitionToken),
+    NoRootKey,
+    NoSigningKey,
+    DbFailure(DBError),
+    Serialization(String),
+}
+
+pub type SessionTokenResult<T> = Result<T,SessionTokenError>;
+
+
+impl fmt::Display for SessionTokenError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionTokenError::UnknownSession(st) => write!(f, "unknown session: {}", st),
+            SessionTokenError::UnknownToken(tt) => write!(f, "unknown token: {}", tt),
+            SessionTokenError::UnknownOwner(owner) => write!(f, "no session for owner: {}", owner),
+            SessionTokenError::TooManySessions(owner) => write!(f, "owner is at the session limit: {}", owner),
+            SessionTokenError::NotTransferable(tt) => write!(f, "token is not transferable: {}", tt),
+            SessionTokenError::NotOwner(tt) => write!(f, "token is not owned by the caller: {}", tt),
+            SessionTokenError::Unauthorized(tt) => write!(f, "transfer of the token is not signed as required: {}", tt),
+            SessionTokenError::NotActive(st) => write!(f, "session is not active for the owner: {}", st),
+            SessionTokenError::NotProven(st) => write!(f, "session owner has not proven possession of its key: {}", st),
+            SessionTokenError::Expired(tt) => write!(f, "token has expired: {}", tt),
+            SessionTokenError::NotYetActive(tt) => write!(f, "token is not active yet: {}", tt),
+            SessionTokenError::Forged(tt) => write!(f, "token signature does not verify: {}", tt),
+            SessionTokenError::Replayed(tt) => write!(f, "spent refresh token was replayed: {}", tt),
+            SessionTokenError::UsedUp(tt) => write!(f, "token has no uses left: {}", tt),
+            SessionTokenError::LimitedUse(tt) => write!(f, "tokens may not be derived from a token with limited uses: {}", tt),
+            SessionTokenError::OutOfScope(tt) => write!(f, "scopes asked for are not held by the token: {}", tt),
+            SessionTokenError::CaveatUnmet(tt) => write!(f, "a caveat on the token does not hold: {}", tt),
+            SessionTokenError::NoRootKey => write!(f, "no root key has been set for caveat tokens"),
+            SessionTokenError::NoSigningKey => write!(f, "no key able to sign JWTs has been set"),
+            SessionTokenError::DbFailure(err) => write!(f, "{}", err),
+            SessionTokenError::Serialization(msg) => write!(f, "serialization: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SessionTokenError {}
+
+
+impl From<DBError> for SessionTokenError {
+    fn from(err : DBError) -> Self {
+        SessionTokenError::DbFailure(err)
+    }
+}
+
+impl From<serde_json::Error> for SessionTokenError {
+    fn from(err : serde_json::Error) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+// the builders only fail when a field without a default is left out
+
+impl From<SessionTimingInfoBuilderError> for SessionTokenError {
+    fn from(err : SessionTimingInfoBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+impl From<TokenTimingInfoBuilderError> for SessionTokenError {
+    fn from(err : TokenTimingInfoBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+impl From<TransferableTokenInfoBuilderError> for SessionTokenError {
+    fn from(err : TransferableTokenInfoBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+impl From<TokenPolicyBuilderError> for SessionTokenError {
+    fn from(err : TokenPolicyBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+impl From<TokenRestrictionsBuilderError> for SessionTokenError {
+    fn from(err : TokenRestrictionsBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
+
+impl From<CaveatContextBuilderError> for SessionTokenError {
+    fn from(err : CaveatContextBuilderError) -> Self {
+        SessionTokenError::Serialization(err.to_string())
+    }
+}
@@ -0,0 +1,181 @@
+//
+//
+use data_encoding::BASE64URL_NOPAD;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::clock::Millis;
+use crate::errors::{SessionTokenError, SessionTokenResult};
+use crate::scopes::ScopeSet;
+use crate::tokens::{SessionToken, TransitionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+type HmacSha256 = Hmac<Sha256>;
+
+
+/**
+ * The key JWTs are signed and checked with.
+ * A service that only checks JWTs signed with Ed25519 may be given just the public key.
+ */
+#[derive(Clone)]
+pub enum JwtKey {
+    Hs256(Vec<u8>),
+    Ed25519(SigningKey),
+    Ed25519Public(VerifyingKey),
+}
+
+
+impl JwtKey {
+    //
+    pub fn alg(&self) -> &'static str {
+        match self {
+            JwtKey::Hs256(_) => "HS256",
+            _ => "EdDSA"
+        }
+    }
+
+    fn sign(&self, message : &[u8]) -> SessionTokenResult<Vec<u8>> {
+        match self {
+            JwtKey::Hs256(secret) => {
+                let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+                mac.update(message);
+                Ok(mac.finalize().into_bytes().to_vec())
+            }
+            JwtKey::Ed25519(signing_key) => Ok(signing_key.sign(message).to_bytes().to_vec()),
+            JwtKey::Ed25519Public(_) => Err(SessionTokenError::NoSigningKey)
+        }
+    }
+
+    fn verify(&self, message : &[u8], signature : &[u8]) -> bool {
+        match self {
+            JwtKey::Hs256(secret) => {
+                let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+                mac.update(message);
+                mac.verify_slice(signature).is_ok()
+            }
+            JwtKey::Ed25519(signing_key) => verify_ed25519(&signing_key.verifying_key(),message,signature),
+            JwtKey::Ed25519Public(verifying_key) => verify_ed25519(verifying_key,message,signature),
+        }
+    }
+}
+
+
+fn verify_ed25519(verifying_key : &VerifyingKey, message : &[u8], signature : &[u8]) -> bool {
+    match Signature::from_slice(signature) {
+        Ok(signature) => verifying_key.verify(message,&signature).is_ok(),
+        _ => false
+    }
+}
+
+
+/**
+ * The claims put into a JWT for a session or a token.
+ * `exp` and `iat` are in seconds, as JWT readers expect; scopes are space separated as in OAuth.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct JwtClaims {
+    #[serde(rename = "sub", skip_serializing_if = "Option::is_none", default)]
+    pub _owner : Option<Ucwid>,
+    #[serde(rename = "sid", skip_serializing_if = "Option::is_none", default)]
+    pub _session : Option<SessionToken>,
+    #[serde(rename = "jti", skip_serializing_if = "Option::is_none", default)]
+    pub _token : Option<TransitionToken>,
+    #[serde(rename = "exp")]
+    pub _expires : u64,
+    #[serde(rename = "iat")]
+    pub _issued_at : u64,
+    #[serde(rename = "scope", skip_serializing_if = "Option::is_none", default)]
+    pub _scope : Option<String>,
+}
+
+
+impl JwtClaims {
+    //
+    pub fn scopes(&self) -> ScopeSet {
+        match &self._scope {
+            Some(scope) => scope.split_whitespace().map(|s| s.to_string()).collect(),
+            _ => ScopeSet::new()
+        }
+    }
+
+    // subject
+    // the token the claims are about, or the session when they are about a session
+
+    pub fn subject(&self) -> String {
+        self._token.clone().or_else(|| self._session.clone()).unwrap_or_default()
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct JwtHeader {
+    alg : String,
+    typ : String,
+}
+
+
+// encode_jwt
+// header.claims.signature, each part base64url without padding
+
+pub fn encode_jwt(claims : &JwtClaims, key : &JwtKey) -> SessionTokenResult<String> {
+    let header = JwtHeader { alg : key.alg().to_string(), typ : "JWT".to_string() };
+    let signing_input = format!("{}.{}", BASE64URL_NOPAD.encode(&serde_json::to_vec(&header)?), BASE64URL_NOPAD.encode(&serde_json::to_vec(claims)?));
+    let signature = key.sign(signing_input.as_bytes())?;
+    Ok(format!("{}.{}", signing_input, BASE64URL_NOPAD.encode(&signature)))
+}
+
+
+// decode_jwt
+// the claims of a JWT whose signature verifies with the key and that has not expired at `now`.
+// The header has to name the algorithm of the key, so a JWT cannot pick how it is checked.
+
+pub fn decode_jwt(jwt : &str, key : &JwtKey, now : Millis) -> SessionTokenResult<JwtClaims> {
+    let forged = || SessionTokenError::Forged(jwt.to_string());
+    let (signing_input, signature) = jwt.rsplit_once('.').ok_or_else(forged)?;
+    let (header, claims) = signing_input.split_once('.').ok_or_else(forged)?;
+    let header : JwtHeader = serde_json::from_slice(&BASE64URL_NOPAD.decode(header.as_bytes()).map_err(|_| forged())?).map_err(|_| forged())?;
+    let signature = BASE64URL_NOPAD.decode(signature.as_bytes()).map_err(|_| forged())?;
+    if header.alg != key.alg() || !key.verify(signing_input.as_bytes(),&signature) {
+        return Err(forged())
+    }
+    let claims : JwtClaims = serde_json::from_slice(&BASE64URL_NOPAD.decode(claims.as_bytes()).map_err(|_| forged())?).map_err(|_| forged())?;
+    if now / 1000 >= claims._expires {
+        return Err(SessionTokenError::Expired(claims.subject()))
+    }
+    Ok(claims)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwts_verify_only_with_their_key_and_algorithm() {
+        let claims = JwtClaims { _owner : Some("owner-1".to_string()), _session : Some("user+abc".to_string()), _token : None, _expires : 100, _issued_at : 10, _scope : Some("media:read media:write".to_string()) };
+        let hs_key = JwtKey::Hs256(b"a secret".to_vec());
+        let ed_key = JwtKey::Ed25519(SigningKey::from_bytes(&[7u8; 32]));
+        let ed_public = match &ed_key {
+            JwtKey::Ed25519(signing_key) => JwtKey::Ed25519Public(signing_key.verifying_key()),
+            _ => unreachable!()
+        };
+        //
+        let hs_jwt = encode_jwt(&claims, &hs_key).unwrap();
+        let ed_jwt = encode_jwt(&claims, &ed_key).unwrap();
+        assert_eq!(decode_jwt(&hs_jwt, &hs_key, 50_000), Ok(claims.clone()));
+        assert_eq!(decode_jwt(&ed_jwt, &ed_public, 50_000), Ok(claims.clone()));
+        assert_eq!(decode_jwt(&ed_jwt, &ed_public, 50_000).unwrap().scopes().len(), 2);
+        assert_eq!(decode_jwt(&hs_jwt, &ed_public, 50_000), Err(SessionTokenError::Forged(hs_jwt.clone())));
+        assert_eq!(decode_jwt(&hs_jwt, &JwtKey::Hs256(b"other".to_vec()), 50_000), Err(SessionTokenError::Forged(hs_jwt.clone())));
+        assert_eq!(decode_jwt(&hs_jwt, &hs_key, 100_000), Err(SessionTokenError::Expired("user+abc".to_string())));
+        assert_eq!(encode_jwt(&claims, &ed_public), Err(SessionTokenError::NoSigningKey));
+    }
+}
@@ -0,0 +1,58 @@
+//! Default session and token tables.
+//!
+//! `LocalSessionTokens` keeps the sessions and tokens of one process in local maps,
+//! with callouts to a shared `DB` supplied by the application.
+//!
+
+pub mod errors;
+pub mod tokens;
+pub mod prefixes;
+pub mod sessions;
+pub mod clock;
+pub mod signing;
+pub mod timing;
+pub mod deadlines;
+pub mod transfer;
+pub mod pairs;
+pub mod scopes;
+pub mod derived;
+pub mod caveats;
+pub mod jwt;
+pub mod proofs;
+pub mod db;
+pub mod memory_db;
+pub mod tables;
+pub mod reaper;
+
+pub use errors::{SessionTokenError, SessionTokenResult};
+pub use tokens::{async_token_lambda, default_token_maker, make_token, token_lambda, AsyncTokenGenerator, FormattedTokenMaker, Hash, SessionToken, StructOrString, Token, TokenEncoding, TokenFormat, TokenGenerator, TransitionToken, Ucwid, DEFAULT_ENTROPY_BYTES, DEFAULT_TOKEN_FORMAT, SESSION_PEFIX};
+pub use prefixes::{PrefixRegistry, TokenKind, TokenPolicy, TokenPolicyBuilder};
+pub use sessions::{AddedSession, SessionLimit, SessionTokenSets, SessionTokenTraits};
+pub use clock::{clock_box, Clock, Millis, MockClock, SystemClock};
+pub use signing::{KeyId, TokenSigner};
+pub use deadlines::DeadlineQueue;
+pub use timing::{SessionTimingInfo, SessionTimingInfoBuilder, TokenTimingInfo, TokenTimingInfoBuilder, GENERAL_DEFAULT_SESSION_TIMEOUT, MINUTES};
+pub use transfer::{TransferAuthorization, TransferSigning, TransferableTokenInfo, TransferableTokenInfoBuilder, TRANSFER_AUTHORIZATION_WINDOW};
+pub use pairs::{TokenPair, ACCESS_PREFIX, REFRESH_PREFIX};
+pub use scopes::{narrow_scopes, scope_set, ScopeSet};
+pub use derived::{TokenRestrictions, TokenRestrictionsBuilder};
+pub use caveats::{Caveat, CaveatContext, CaveatContextBuilder, CaveatToken};
+pub use jwt::{decode_jwt, encode_jwt, JwtClaims, JwtKey};
+pub use proofs::{challenge_message, UcwidKey, CHALLENGE_TIMEOUT};
+pub use db::{DBError, DBResult, TokenUse, DB};
+pub use memory_db::InMemoryDB;
+pub use tables::{LocalSessionTokens, TokenTables};
+pub use reaper::{reaper_report, spawn_reaper, ReaperHandle};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+// set_from_stored
+// helper for the set_all methods -- a field missing from the stored record keeps its current value
+
+pub(crate) fn set_from_stored<T>(field : &mut T, stored : &serde_json::Value) -> SessionTokenResult<()> where T: serde::de::DeserializeOwned {
+    if !stored.is_null() {
+        *field = serde_json::from_value(stored.clone())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+//
+//
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::db::{DBError, DBResult, TokenUse, DB};
+use crate::tokens::{Hash, SessionToken, TransitionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+const SALT_BYTES : usize = 32;
+
+
+struct InMemoryTables {
+    _salt : [u8; SALT_BYTES],
+    _session_hashes : RwLock<HashMap<SessionToken,Hash>>,
+    _hash_to_session : RwLock<HashMap<Hash,SessionToken>>,
+    _key_values : RwLock<HashMap<TransitionToken,String>>,
+    _use_counts : RwLock<HashMap<TransitionToken,u32>>,
+}
+
+
+/**
+ * A DB kept in the memory of the running process.
+ * Clones share the same tables, so one InMemoryDB may be handed to several LocalSessionTokens
+ * in the same way that a shared store is handed to several processes.
+ * Session hashes are SHA-256 digests of a per instance salt, the session token, and the owner.
+ */
+#[derive(Clone)]
+pub struct InMemoryDB {
+    _tables : Arc<InMemoryTables>,
+}
+
+
+impl InMemoryDB {
+    //
+    pub fn new() -> InMemoryDB {
+        let mut salt = [0u8; SALT_BYTES];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let tables = InMemoryTables {
+            _salt : salt,
+            _session_hashes : RwLock::new(HashMap::new()),
+            _hash_to_session : RwLock::new(HashMap::new()),
+            _key_values : RwLock::new(HashMap::new()),
+            _use_counts : RwLock::new(HashMap::new()),
+        };
+        InMemoryDB { _tables : Arc::new(tables) }
+    }
+
+    fn session_hash(&self, session_token : & SessionToken, ownership_key : & Ucwid) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(self._tables._salt);
+        hasher.update(session_token.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(ownership_key.as_bytes());
+        to_hex(&hasher.finalize())
+    }
+}
+
+impl Default for InMemoryDB {
+    fn default() -> Self {
+        InMemoryDB::new()
+    }
+}
+
+
+fn to_hex(bytes : &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// compare without leaving early, so that the time taken does not tell how much of a hash matched
+fn same_hash(a : &str, b : &str) -> bool {
+    if a.len() != b.len() {
+        return false
+    }
+    a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn poisoned<T>(_err : T) -> DBError {
+    DBError::Failed("in memory table lock poisoned".to_string())
+}
+
+
+
+#[async_trait]
+impl DB for InMemoryDB {
+    //
+    async fn set_session_key_value(&self, session_token : & SessionToken, ownership_key : Ucwid ) -> DBResult<Hash> {
+        let hh = self.session_hash(session_token, &ownership_key);
+        let mut sessions = self._tables._session_hashes.write().map_err(poisoned)?;
+        let mut hashes = self._tables._hash_to_session.write().map_err(poisoned)?;
+        if let Some(old_hh) = sessions.insert(session_token.to_string(), hh.to_string()) {
+            hashes.remove(&old_hh);
+        }
+        hashes.insert(hh.to_string(), session_token.to_string());
+        Ok(hh)
+    }
+
+    async fn del_session_key_value(&self, session_token : & SessionToken ) -> DBResult<bool> {
+        let mut sessions = self._tables._session_hashes.write().map_err(poisoned)?;
+        let mut hashes = self._tables._hash_to_session.write().map_err(poisoned)?;
+        match sessions.remove(session_token) {
+            Some(hh) => {
+                hashes.remove(&hh);
+                Ok(true)
+            }
+            _ => Ok(false)
+        }
+    }
+
+    async fn set_key_value(&self, token : & TransitionToken, value : &str )  -> DBResult<()> {
+        let mut key_values = self._tables._key_values.write().map_err(poisoned)?;
+        key_values.insert(token.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn get_key_value(&self, token : & TransitionToken )  -> DBResult<Option<String>> {
+        let key_values = self._tables._key_values.read().map_err(poisoned)?;
+        Ok(key_values.get(token).cloned())
+    }
+
+    async fn del_key_value(&self, token : & TransitionToken )  -> DBResult<()> {
+        let mut key_values = self._tables._key_values.write().map_err(poisoned)?;
+        let mut use_counts = self._tables._use_counts.write().map_err(poisoned)?;
+        key_values.remove(token);
+        use_counts.remove(token);
+        Ok(())
+    }
+
+    // the hash identifies the session; it is only good if recomputing it with the claimed owner gives the same hash
+    async fn check_hash(&self, hh_unidentified : &str, ownership_key : &Ucwid )  -> DBResult<bool> {
+        let session_token = {
+            let hashes = self._tables._hash_to_session.read().map_err(poisoned)?;
+            match hashes.get(hh_unidentified) {
+                Some(st) => st.to_string(),
+                _ => return Ok(false)
+            }
+        };
+        let hh = self.session_hash(&session_token, ownership_key);
+        Ok(same_hash(&hh, hh_unidentified))
+    }
+
+    async fn set_use_count(&self, token : & TransitionToken, uses : u32 )  -> DBResult<()> {
+        let mut use_counts = self._tables._use_counts.write().map_err(poisoned)?;
+        use_counts.insert(token.to_string(), uses);
+        Ok(())
+    }
+
+    // the value and the count are both locked for the whole step, so two callers cannot take the last use
+    async fn take_use(&self, token : & TransitionToken )  -> DBResult<TokenUse> {
+        let key_values = self._tables._key_values.write().map_err(poisoned)?;
+        let mut use_counts = self._tables._use_counts.write().map_err(poisoned)?;
+        if !key_values.contains_key(token) {
+            return Ok(TokenUse::Refused)
+        }
+        match use_counts.get_mut(token) {
+            Some(0) => Ok(TokenUse::Refused),
+            Some(uses) => {
+                *uses -= 1;
+                Ok(TokenUse::Taken(*uses))
+            }
+            _ => Ok(TokenUse::Unlimited)
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn hash_checks_owner() {
+        let db = InMemoryDB::new();
+        let session = "user+abc".to_string();
+        let hh = db.set_session_key_value(&session, "owner-1".to_string()).await.unwrap();
+        assert!(db.check_hash(&hh, &"owner-1".to_string()).await.unwrap());
+        assert!(!db.check_hash(&hh, &"owner-2".to_string()).await.unwrap());
+        assert!(db.del_session_key_value(&session).await.unwrap());
+        assert!(!db.check_hash(&hh, &"owner-1".to_string()).await.unwrap());
+    }
+
+    #[async_std::test]
+    async fn clones_share_values() {
+        let db = InMemoryDB::new();
+        let other = db.clone();
+        let token = "media+xyz".to_string();
+        db.set_key_value(&token, "{}").await.unwrap();
+        assert_eq!(other.get_key_value(&token).await.unwrap(), Some("{}".to_string()));
+        other.del_key_value(&token).await.unwrap();
+        assert_eq!(db.get_key_value(&token).await.unwrap(), None);
+    }
+
+    #[async_std::test]
+    async fn backs_local_session_tokens() {
+        use crate::tables::{LocalSessionTokens, TokenTables};
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let session = "user+abc".to_string();
+        let owner = "owner-1".to_string();
+        tables.add_session(&session, &owner, None, Some(true)).await.unwrap();
+        assert!(tables.active_session(&session, &owner).await.unwrap());
+        assert!(!tables.active_session(&session, &"owner-2".to_string()).await.unwrap());
+    }
+}
@@ -0,0 +1,34 @@
+//
+//
+use crate::tokens::{SessionToken, TransitionToken};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+pub const ACCESS_PREFIX : &str = "access+";
+pub const REFRESH_PREFIX : &str = "refresh+";
+
+
+/**
+ * A short lived access token and the long lived refresh token that may be redeemed for the next one.
+ * Both are bound to the session they were issued under.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+pub struct TokenPair {
+    pub _access : TransitionToken,
+    pub _refresh : TransitionToken,
+}
+
+
+/**
+ * What is kept for a live refresh token: its session and the timeouts given to the tokens issued from it.
+ * The family of a refresh token is its session; replaying a spent refresh token ends the family.
+ */
+#[derive(Clone)]
+pub(crate) struct RefreshFamily {
+    pub(crate) _session : SessionToken,
+    pub(crate) _access_timeout : i32,
+    pub(crate) _refresh_timeout : i32,
+}
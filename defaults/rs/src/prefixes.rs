@@ -0,0 +1,133 @@
+//
+//
+use std::collections::HashMap;
+
+use derive_builder::Builder;
+
+use crate::tokens::{Token, SESSION_PEFIX};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+pub enum TokenKind {
+    Session,
+    Transition,
+}
+
+
+/**
+ * What applies to the tokens made with one prefix.
+ * A timeout of None leaves the token with the default allotment.
+ * Tokens of a kind that is not transferable may not be added as transferable tokens,
+ * and tokens of a sellable kind are put up for sale when they are added.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Builder)]
+pub struct TokenPolicy {
+    #[builder(default = "TokenKind::Transition")]
+    pub _kind : TokenKind,
+    #[builder(default = "None")]
+    pub _timeout : Option<i32>,
+    #[builder(default = "true")]
+    pub _transferable : bool,
+    #[builder(default = "false")]
+    pub _sellable : bool,
+}
+
+
+/**
+ * Maps token prefixes (e.g. "media+", "order+") to their policies.
+ * A token is classified by the longest registered prefix it starts with.
+ * The session prefix is registered from the start.
+ */
+#[derive(Clone)]
+pub struct PrefixRegistry {
+    _policies : HashMap<String,TokenPolicy>,
+}
+
+
+impl PrefixRegistry {
+    //
+    pub fn new() -> PrefixRegistry {
+        let mut registry = PrefixRegistry { _policies : HashMap::new() };
+        let session_policy = TokenPolicy { _kind : TokenKind::Session, _timeout : None, _transferable : false, _sellable : false };
+        registry.register(SESSION_PEFIX, session_policy);
+        registry
+    }
+
+    pub fn register(&mut self, prefix : &str, policy : TokenPolicy) {
+        self._policies.insert(prefix.to_string(), policy);
+    }
+
+    pub fn unregister(&mut self, prefix : &str) -> Option<TokenPolicy> {
+        self._policies.remove(prefix)
+    }
+
+    pub fn policy(&self, prefix : &str) -> Option<&TokenPolicy> {
+        self._policies.get(prefix)
+    }
+
+    // classify
+    // the prefix a token was made with and the policy for it, if the prefix is registered
+
+    pub fn classify(&self, token : &str) -> Option<(&str,&TokenPolicy)> {
+        let mut found : Option<(&str,&TokenPolicy)> = None;
+        for (prefix, policy) in &self._policies {
+            if token.starts_with(prefix.as_str()) && found.is_none_or(|(fp, _)| prefix.len() > fp.len()) {
+                found = Some((prefix.as_str(), policy));
+            }
+        }
+        found
+    }
+
+    // apply_kind
+    // a token made by the creator is turned into the kind registered for its prefix
+
+    pub fn apply_kind(&self, token : Token) -> Token {
+        let text = match &token {
+            Token::SessionToken(st) => st,
+            Token::TransitionToken(tt) => tt,
+        };
+        match self.classify(text).map(|(_, policy)| policy._kind) {
+            Some(TokenKind::Session) => match token {
+                Token::TransitionToken(tt) => Token::SessionToken(tt),
+                _ => token
+            },
+            Some(TokenKind::Transition) => match token {
+                Token::SessionToken(st) => Token::TransitionToken(st),
+                _ => token
+            },
+            _ => token
+        }
+    }
+}
+
+impl Default for PrefixRegistry {
+    fn default() -> Self {
+        PrefixRegistry::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_classifies() {
+        let mut registry = PrefixRegistry::new();
+        registry.register("media+", TokenPolicyBuilder::default()._timeout(Some(1000)).build().unwrap());
+        registry.register("media+video+", TokenPolicyBuilder::default()._kind(TokenKind::Session).build().unwrap());
+        //
+        assert_eq!(registry.classify("media+abc").map(|(p, _)| p), Some("media+"));
+        assert_eq!(registry.classify("media+video+abc").map(|(p, _)| p), Some("media+video+"));
+        assert!(registry.classify("order+abc").is_none());
+        assert_eq!(registry.apply_kind(Token::TransitionToken("media+video+abc".to_string())), Token::SessionToken("media+video+abc".to_string()));
+        assert_eq!(registry.apply_kind(Token::TransitionToken("user+abc".to_string())), Token::SessionToken("user+abc".to_string()));
+    }
+}
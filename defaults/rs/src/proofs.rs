@@ -0,0 +1,115 @@
+//
+//
+use data_encoding::BASE64URL_NOPAD;
+use ed25519_dalek::Verifier;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::timing::MINUTES;
+use crate::tokens::{SessionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+// how long a client has to answer a session challenge
+pub const CHALLENGE_TIMEOUT : i32 = 2*MINUTES;
+
+const NONCE_BYTES : usize = 32;
+
+
+/**
+ * The public key a Ucwid wraps.
+ * A Ucwid carrying a key is written `ed25519:<key>` or `p256:<key>`, the key in base64url without padding
+ * (the 32 key bytes for Ed25519, the SEC1 encoded point for P-256).
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum UcwidKey {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    P256(p256::ecdsa::VerifyingKey),
+}
+
+
+impl UcwidKey {
+    //
+    pub fn from_ucwid(ucwid : &Ucwid) -> Option<UcwidKey> {
+        let (alg, key) = ucwid.split_once(':')?;
+        let key = BASE64URL_NOPAD.decode(key.as_bytes()).ok()?;
+        match alg {
+            "ed25519" => {
+                let key : [u8; 32] = key.try_into().ok()?;
+                ed25519_dalek::VerifyingKey::from_bytes(&key).ok().map(UcwidKey::Ed25519)
+            }
+            "p256" => p256::ecdsa::VerifyingKey::from_sec1_bytes(&key).ok().map(UcwidKey::P256),
+            _ => None
+        }
+    }
+
+    pub fn to_ucwid(&self) -> Ucwid {
+        match self {
+            UcwidKey::Ed25519(key) => format!("ed25519:{}", BASE64URL_NOPAD.encode(key.as_bytes())),
+            UcwidKey::P256(key) => format!("p256:{}", BASE64URL_NOPAD.encode(key.to_encoded_point(true).as_bytes())),
+        }
+    }
+
+    // verify
+    // P-256 signatures may be given either as r||s or DER encoded
+
+    pub fn verify(&self, message : &[u8], signature : &[u8]) -> bool {
+        match self {
+            UcwidKey::Ed25519(key) => match ed25519_dalek::Signature::from_slice(signature) {
+                Ok(signature) => key.verify(message,&signature).is_ok(),
+                _ => false
+            },
+            UcwidKey::P256(key) => {
+                let signature = p256::ecdsa::Signature::from_slice(signature).or_else(|_| p256::ecdsa::Signature::from_der(signature));
+                match signature {
+                    Ok(signature) => key.verify(message,&signature).is_ok(),
+                    _ => false
+                }
+            }
+        }
+    }
+}
+
+
+// make_nonce
+
+pub fn make_nonce() -> String {
+    let mut bytes = [0u8; NONCE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+
+// challenge_message
+// what the client signs -- the nonce is bound to the session so that it cannot be answered for another one
+
+pub fn challenge_message(session_token : &SessionToken, nonce : &str) -> Vec<u8> {
+    format!("{}.{}", session_token, nonce).into_bytes()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+
+    #[test]
+    fn ucwid_keys_check_signatures_of_either_kind() {
+        let ed_signer = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let p256_signer = p256::ecdsa::SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let ed_ucwid = UcwidKey::Ed25519(ed_signer.verifying_key()).to_ucwid();
+        let p256_ucwid = UcwidKey::P256(*p256_signer.verifying_key()).to_ucwid();
+        let message = challenge_message(&"user+abc".to_string(), "nonce");
+        //
+        let ed_signature = ed_signer.sign(&message).to_bytes();
+        let p256_signature : p256::ecdsa::Signature = p256_signer.sign(&message);
+        assert!(UcwidKey::from_ucwid(&ed_ucwid).unwrap().verify(&message, &ed_signature));
+        assert!(UcwidKey::from_ucwid(&p256_ucwid).unwrap().verify(&message, &p256_signature.to_bytes()));
+        assert!(UcwidKey::from_ucwid(&p256_ucwid).unwrap().verify(&message, p256_signature.to_der().as_bytes()));
+        assert!(!UcwidKey::from_ucwid(&ed_ucwid).unwrap().verify(&challenge_message(&"user+xyz".to_string(), "nonce"), &ed_signature));
+        assert!(UcwidKey::from_ucwid(&"owner-1".to_string()).is_none());
+    }
+}
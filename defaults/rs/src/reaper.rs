@@ -0,0 +1,104 @@
+//
+//
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_std::sync::Mutex;
+use async_std::task::{self, JoinHandle};
+use futures::channel::oneshot;
+use futures::future::{self, Either};
+use timer_future::TimerFuture;
+
+use crate::db::DB;
+use crate::tables::{LocalSessionTokens, TokenTables};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * Called after each pass of the reaper with the number of entries the pass removed.
+ */
+#[allow(non_camel_case_types)]
+pub type reaper_report = Box<dyn Fn(usize) + Send + Sync>;
+
+
+/**
+ * Returned by `spawn_reaper`. The reaper runs until `shutdown` is called or the handle is dropped.
+ */
+pub struct ReaperHandle {
+    _stop : Option<oneshot::Sender<()>>,
+    _task : JoinHandle<usize>,
+}
+
+impl ReaperHandle {
+    /// Stops the reaper, waiting for a pass in progress to finish.
+    /// Returns the total number of entries removed while the reaper ran.
+    pub async fn shutdown(mut self) -> usize {
+        if let Some(stop) = self._stop.take() {
+            stop.send(()).ok();
+        }
+        self._task.await
+    }
+}
+
+
+/**
+ * Spawns a task that calls `decrement_timers` on the tables once every `interval`.
+ * Each pass removes what has run out and promotes the pending tokens whose not-before instant has come.
+ * The wait between passes is a `TimerFuture`, which is raced against the shutdown signal
+ * so that shutting down does not have to wait out the interval.
+ */
+pub fn spawn_reaper<D>(tables : Arc<Mutex<LocalSessionTokens<D>>>, interval : Duration, report : Option<reaper_report>) -> ReaperHandle
+        where D: DB + std::marker::Unpin + 'static {
+    //
+    let (stop_sender, mut stop_receiver) = oneshot::channel::<()>();
+    let task = task::spawn(async move {
+        let mut total_removed : usize = 0;
+        // the select ends on the right when shutdown was called or the handle was dropped
+        while let Either::Left(_) = future::select(TimerFuture::new(interval), &mut stop_receiver).await {
+            let removed = tables.lock().await.decrement_timers().await;
+            total_removed += removed;
+            if let Some(report_fn) = &report {
+                report_fn(removed);
+            }
+        }
+        total_removed
+    });
+    //
+    ReaperHandle { _stop : Some(stop_sender), _task : task }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::memory_db::InMemoryDB;
+    use crate::timing::MINUTES;
+
+    #[async_std::test]
+    async fn reaper_removes_expired_sessions() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &"owner-1".to_string(), Some("media+xyz".to_string()), None).await.unwrap();
+        let tables = Arc::new(Mutex::new(tables));
+        //
+        let (sender, receiver) = std::sync::mpsc::channel::<usize>();
+        let sender = std::sync::Mutex::new(sender);
+        let report : reaper_report = Box::new(move |removed| {
+            if removed > 0 {
+                sender.lock().unwrap().send(removed).ok();
+            }
+        });
+        let handle = spawn_reaper(tables.clone(), Duration::from_millis(5), Some(report));
+        clock.advance((61*MINUTES) as u64);
+        //
+        let removed = task::spawn_blocking(move || receiver.recv_timeout(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(removed, 2);     // the session and its bounded token
+        assert!(tables.lock().await.get_session_time_left(&session).is_err());
+        assert_eq!(handle.shutdown().await, 2);
+    }
+}
@@ -0,0 +1,48 @@
+//
+//
+use std::collections::BTreeSet;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * The permissions a token carries, e.g. "media:read" or "order:cancel".
+ * The strings are the application's own; a token without scopes permits nothing.
+ */
+pub type ScopeSet = BTreeSet<String>;
+
+
+// scope_set
+// helper for making a ScopeSet from string literals
+
+pub fn scope_set(scopes : &[&str]) -> ScopeSet {
+    scopes.iter().map(|scope| scope.to_string()).collect()
+}
+
+
+// narrow_scopes
+// the scopes a token made from another may have -- only those the other already holds.
+// None if any requested scope is not held, so that asking for more than is held is an error rather than a silent drop.
+
+pub fn narrow_scopes(held : &ScopeSet, requested : &ScopeSet) -> Option<ScopeSet> {
+    if requested.is_subset(held) {
+        Some(requested.clone())
+    } else {
+        None
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn narrowing_never_widens() {
+        let held = scope_set(&["media:read", "media:write"]);
+        assert_eq!(narrow_scopes(&held, &scope_set(&["media:read"])), Some(scope_set(&["media:read"])));
+        assert_eq!(narrow_scopes(&held, &scope_set(&[])), Some(ScopeSet::new()));
+        assert_eq!(narrow_scopes(&held, &scope_set(&["media:read", "order:cancel"])), None);
+    }
+}
@@ -0,0 +1,71 @@
+//
+//
+use std::collections::HashSet;
+
+use crate::tokens::{Hash, SessionToken, TransitionToken};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+pub trait SessionTokenTraits {
+    fn new() -> Self;
+    fn clear(&mut self) -> ();
+}
+
+
+/**
+ * Collects all the tokens belonging to a session into one data structure.
+ * This object refers to two sets
+ * 1. one that contains the tokens that must be destroyed when the session is destroyed.
+ * 2. a second that contains the tokens that may be transfered to another session and owner.
+ */
+#[derive(Clone)]
+pub struct SessionTokenSets {
+    pub session_bounded : HashSet<TransitionToken>,
+    pub session_carries : HashSet<TransitionToken>
+}
+
+
+impl SessionTokenTraits for SessionTokenSets {
+    fn new() -> SessionTokenSets {
+        let bounded = HashSet::new();
+        let carries = HashSet::new();
+        SessionTokenSets { session_bounded : bounded, session_carries : carries }
+    }
+    fn clear(&mut self) {
+        self.session_bounded.clear();
+        self.session_carries.clear();
+    }
+}
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * How many sessions an owner may have at once.
+ * When an owner is at the limit, `Reject` turns the new session away, while `EvictOldest` ends the
+ * owner's oldest sessions to make room. `Single` is the same as evicting down to one session.
+ */
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+#[derive(Default)]
+pub enum SessionLimit {
+    #[default]
+    Unlimited,
+    Reject(usize),
+    EvictOldest(usize),
+    Single,
+}
+
+/**
+ * What add_session returns: the hash of the session when it is shared,
+ * and the sessions of the same owner that were ended to make room for it.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+pub struct AddedSession {
+    pub _hash : Option<Hash>,
+    pub _displaced : Vec<SessionToken>,
+}
@@ -0,0 +1,142 @@
+//
+//
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::clock::Millis;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub type KeyId = u32;
+
+const SIGNATURE_SEPARATOR : char = '.';
+
+
+/**
+ * Signs tokens so that they may be checked without looking them up.
+ * A signed token is the token as made by the token creator followed by the issue time, the key id,
+ * and an HMAC-SHA256 over all that comes before it: `<prefix><random body>.<issued at>.<key id>.<mac>`.
+ * Several keys may be active at once. The most recently added key signs, and all of them verify,
+ * so that a new key may be put in place while tokens signed with the old one are still live.
+ */
+#[derive(Clone)]
+pub struct TokenSigner {
+    _keys : Vec<(KeyId,Vec<u8>)>,       // the last key signs
+}
+
+
+impl TokenSigner {
+    //
+    pub fn new(key_id : KeyId, secret : &[u8]) -> TokenSigner {
+        TokenSigner { _keys : vec![(key_id, secret.to_vec())] }
+    }
+
+    // add_key
+    // the new key signs from now on -- a key with the same id is replaced
+
+    pub fn add_key(&mut self, key_id : KeyId, secret : &[u8]) {
+        self._keys.retain(|(kid, _)| *kid != key_id);
+        self._keys.push((key_id, secret.to_vec()));
+    }
+
+    // retire_key
+    // tokens signed with a retired key no longer verify -- the signing key may not be retired
+
+    pub fn retire_key(&mut self, key_id : KeyId) -> bool {
+        if self.signing_key() == key_id {
+            return false
+        }
+        let count = self._keys.len();
+        self._keys.retain(|(kid, _)| *kid != key_id);
+        count != self._keys.len()
+    }
+
+    pub fn signing_key(&self) -> KeyId {
+        match self._keys.last() {
+            Some((kid, _)) => *kid,
+            _ => 0
+        }
+    }
+
+    pub fn sign(&self, token : &str, issued_at : Millis) -> String {
+        let (key_id, secret) = match self._keys.last() {
+            Some(key) => key,
+            _ => return token.to_string()
+        };
+        let signed = format!("{}{}{}{}{}", token, SIGNATURE_SEPARATOR, issued_at, SIGNATURE_SEPARATOR, key_id);
+        let mac = BASE64URL_NOPAD.encode(&keyed_mac(secret, &signed).finalize().into_bytes());
+        format!("{}{}{}", signed, SIGNATURE_SEPARATOR, mac)
+    }
+
+    pub fn verify(&self, token : &str) -> bool {
+        self.issued_at(token).is_some()
+    }
+
+    // issued_at
+    // the issue time of a token that verifies, None for any token that is forged or malformed
+
+    pub fn issued_at(&self, token : &str) -> Option<Millis> {
+        let (signed, mac) = token.rsplit_once(SIGNATURE_SEPARATOR)?;
+        let mut parts = signed.rsplitn(3, SIGNATURE_SEPARATOR);
+        let key_id : KeyId = parts.next()?.parse().ok()?;
+        let issued_at : Millis = parts.next()?.parse().ok()?;
+        let body = parts.next()?;
+        if body.is_empty() {
+            return None
+        }
+        let mac = BASE64URL_NOPAD.decode(mac.as_bytes()).ok()?;
+        let (_, secret) = self._keys.iter().find(|(kid, _)| *kid == key_id)?;
+        match keyed_mac(secret, signed).verify_slice(&mac) {     // constant time comparison
+            Ok(_) => Some(issued_at),
+            _ => None
+        }
+    }
+}
+
+
+fn keyed_mac(secret : &[u8], signed : &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(signed.as_bytes());
+    mac
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_forged_and_malformed_tokens() {
+        let signer = TokenSigner::new(1, b"first secret");
+        let token = signer.sign("media+abc123", 1000);
+        assert_eq!(signer.issued_at(&token), Some(1000));
+        //
+        let forged = token.replacen("abc123", "abc124", 1);
+        assert!(!signer.verify(&forged));
+        assert!(!signer.verify(&token.replacen(".1000.", ".2000.", 1)));
+        assert!(!signer.verify("media+abc123"));
+        assert!(!signer.verify("...."));
+        assert!(!TokenSigner::new(1, b"other secret").verify(&token));
+    }
+
+    #[test]
+    fn old_keys_verify_until_retired() {
+        let mut signer = TokenSigner::new(1, b"first secret");
+        let old_token = signer.sign("media+abc", 1000);
+        signer.add_key(2, b"second secret");
+        let new_token = signer.sign("media+def", 2000);
+        assert!(new_token.starts_with("media+def.2000.2."));
+        assert!(signer.verify(&old_token));
+        assert!(signer.verify(&new_token));
+        //
+        assert!(!signer.retire_key(2));
+        assert!(signer.retire_key(1));
+        assert!(!signer.verify(&old_token));
+        assert!(signer.verify(&new_token));
+    }
+}
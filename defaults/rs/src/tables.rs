@@ -0,0 +1,2580 @@
+//
+//
+use std::collections::{HashSet, HashMap};
+//
+use async_trait::async_trait;
+use futures::future;
+
+use serde_json::{Value};
+
+use crate::db::{TokenUse, DB};
+use crate::deadlines::DeadlineQueue;
+use crate::errors::{SessionTokenError, SessionTokenResult};
+use crate::prefixes::{PrefixRegistry, TokenPolicy};
+use crate::sessions::{AddedSession, SessionLimit, SessionTokenSets, SessionTokenTraits};
+use crate::clock::{clock_box, Millis, SystemClock};
+use crate::signing::TokenSigner;
+use crate::timing::{SessionTimingInfo, SessionTimingInfoBuilder, TokenTimingInfo, TokenTimingInfoBuilder, GENERAL_DEFAULT_SESSION_TIMEOUT};
+use crate::tokens::{async_token_lambda, default_token_maker, token_lambda, Hash, SessionToken, StructOrString, Token, TransitionToken, Ucwid, SESSION_PEFIX};
+use crate::transfer::{TransferAuthorization, TransferSigning, TransferableTokenInfo, TransferableTokenInfoBuilder, TRANSFER_AUTHORIZATION_WINDOW};
+use crate::pairs::{RefreshFamily, TokenPair, ACCESS_PREFIX, REFRESH_PREFIX};
+use crate::scopes::{narrow_scopes, ScopeSet};
+use crate::derived::TokenRestrictions;
+use crate::caveats::{opaque_id, Caveat, CaveatContextBuilder, CaveatToken};
+use crate::jwt::{decode_jwt, encode_jwt, JwtClaims, JwtKey};
+use crate::proofs::{challenge_message, make_nonce, UcwidKey, CHALLENGE_TIMEOUT};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+#[async_trait]
+pub trait TokenTables<D: DB> {
+    type Jsonable;
+    //
+    fn new(db : D, token_creator : Option<token_lambda>) -> Self;
+    //
+    async fn decrement_timers(&mut self) -> usize;
+    fn set_token_creator(&mut self, token_creator : Option<token_lambda>) -> ();
+    fn set_async_token_creator(&mut self, token_creator : Option<async_token_lambda>) -> ();
+    fn set_clock(&mut self, clock : Option<clock_box>) -> ();
+    fn set_token_signer(&mut self, signer : Option<TokenSigner>) -> ();
+    fn set_caveat_root_key(&mut self, root_key : Option<Vec<u8>>) -> ();
+    fn set_jwt_key(&mut self, key : Option<JwtKey>) -> ();
+    //
+    fn set_session_limit(&mut self, limit : SessionLimit) -> ();
+    fn set_transfer_signing(&mut self, signing : TransferSigning) -> ();
+    async fn add_session(&mut self, session_token : & SessionToken, ownership_key : & Ucwid, o_t_token : Option<TransitionToken>, shared : Option<bool> ) -> SessionTokenResult<AddedSession>;
+    async fn active_session(&self, session_token : & SessionToken, ownership_key : & Ucwid) -> SessionTokenResult<bool>;
+    async fn destroy_session(&mut self, token : & TransitionToken) -> SessionTokenResult<()>;
+    fn sessions_for_owner(&self, ownership_key : & Ucwid) -> Vec<SessionToken>;
+    async fn destroy_all_sessions_for_owner(&mut self, ownership_key : & Ucwid) -> SessionTokenResult<usize>;
+    fn allow_session_detach(&mut self, session_token : SessionToken) -> SessionTokenResult<()>;
+    async fn detach_session(&mut self, session_token : SessionToken) -> SessionTokenResult<()>;
+    async fn attach_session(&mut self, session_token : SessionToken) -> SessionTokenResult<()>;
+    async fn rotate_session(&mut self, session_token : & SessionToken, grace_period : Option<i32>) -> SessionTokenResult<SessionToken>;
+    fn require_proof_of_possession(&mut self, required : bool) -> ();
+    fn issue_session_challenge(&mut self, session_token : & SessionToken) -> SessionTokenResult<String>;
+    fn answer_session_challenge(&mut self, session_token : & SessionToken, signature : &[u8]) -> SessionTokenResult<()>;
+    //
+    fn register_token_prefix(&mut self, prefix : &str, policy : TokenPolicy) -> ();
+    fn classify_token(&self, token : &str) -> Option<TokenPolicy>;
+    fn create_token(&self, prefix : Option<String> ) -> Token;
+    async fn create_token_async(&self, prefix : Option<String> ) -> Token;
+    fn mint_caveat_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<CaveatToken>;
+    fn verify_caveat_token(&self, encoded : &str, scope : Option<&str>) -> SessionTokenResult<TransitionToken>;
+    fn session_jwt(&mut self, session_token : & SessionToken) -> SessionTokenResult<String>;
+    fn token_jwt(&mut self, t_token : & TransitionToken) -> SessionTokenResult<String>;
+    fn verify_jwt(&self, jwt : &str) -> SessionTokenResult<JwtClaims>;
+    async fn add_token(&mut self, token : &TransitionToken, value : StructOrString<Self::Jsonable> ) -> SessionTokenResult<()>;
+    async fn transition_token_is_active(&mut self, token : & TransitionToken) -> SessionTokenResult<String>;
+    #[allow(clippy::wrong_self_convention)]     // the name the other language defaults give it
+    fn from_token(&self, token : TransitionToken) -> SessionTokenResult<Ucwid>;
+    async fn add_transferable_token(&mut self,  t_token : & TransitionToken, value : StructOrString<Self::Jsonable>, ownership_key : & Ucwid ) -> SessionTokenResult<()>;
+    async fn add_session_bounded_token(&mut self,  t_token : & TransitionToken, value : StructOrString<Self::Jsonable>, ownership_key : & Ucwid )  -> SessionTokenResult<()>;
+    async fn acquire_token(&mut self, t_token : & TransitionToken, session_token : & SessionToken, owner : & Ucwid) -> SessionTokenResult<()>;
+    fn token_is_transferable(&self,  t_token : &TransitionToken) -> bool;
+    fn set_token_scopes(&mut self, t_token : & TransitionToken, scopes : ScopeSet) -> SessionTokenResult<()>;
+    fn narrow_token_scopes(&mut self, t_token : & TransitionToken, keep : & ScopeSet) -> SessionTokenResult<()>;
+    fn token_scopes(&self, t_token : & TransitionToken) -> SessionTokenResult<ScopeSet>;
+    fn check_token_scope(&self, t_token : & TransitionToken, scope : &str) -> SessionTokenResult<bool>;
+    //
+    async fn transfer_token(&mut self,  t_token : & TransitionToken, yielder_key : & Ucwid,  receiver_key : & Ucwid )  -> SessionTokenResult<()>;
+    async fn transfer_token_signed(&mut self, authorization : TransferAuthorization) -> SessionTokenResult<()>;
+    fn transfer_proofs(&self, t_token : & TransitionToken) -> Vec<TransferAuthorization>;
+    async fn destroy_token(&mut self, token : & TransitionToken) -> SessionTokenResult<()>;
+    async fn derive_token(&mut self, parent : & TransitionToken, restrictions : TokenRestrictions) -> SessionTokenResult<TransitionToken>;
+    fn derived_tokens(&self, parent : & TransitionToken) -> Vec<TransitionToken>;
+    async fn set_token_uses(&mut self, t_token : & TransitionToken, uses : u32) -> SessionTokenResult<()>;
+    async fn consume_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<String>;
+    //
+    async fn issue_token_pair(&mut self, session_token : & SessionToken, access_timeout : i32, refresh_timeout : i32) -> SessionTokenResult<TokenPair>;
+    async fn redeem_refresh_token(&mut self, refresh_token : & TransitionToken, rotate : bool) -> SessionTokenResult<TokenPair>;
+
+    //
+    fn set_general_session_timeout(&mut self, timeout : i32) -> ();
+    async fn set_session_timeout(&mut self, session_token : & SessionToken, timeout : i32) -> SessionTokenResult<()>;
+    fn get_session_timeout(&mut self, session_token : & SessionToken) -> SessionTokenResult<i32>;
+    fn get_session_time_left(&mut self, session_token : & SessionToken) -> SessionTokenResult<i32>;
+    async fn set_session_idle_timeout(&mut self, session_token : & SessionToken, idle_timeout : Option<i32>) -> SessionTokenResult<()>;
+    async fn touch_session(&mut self, session_token : & SessionToken) -> SessionTokenResult<()>;
+    //
+    fn set_general_token_timeout(&mut self, timeout : i32) -> ();
+    fn set_disownment_token_timeout(&mut self, t_token : & TransitionToken, timeout : i32) -> SessionTokenResult<()>;
+    fn set_token_timeout(&mut self, t_token : & TransitionToken,timeout : i32) -> SessionTokenResult<()>;
+    fn get_token_timeout(&mut self, t_token : & TransitionToken) -> SessionTokenResult<i32>;
+    fn get_token_time_left(&mut self, t_token : & TransitionToken)  ->  SessionTokenResult<i32>;
+    fn set_token_not_before(&mut self, t_token : & TransitionToken, not_before : Millis) -> SessionTokenResult<()>;
+    fn set_token_idle_timeout(&mut self, t_token : & TransitionToken, idle_timeout : Option<i32>) -> SessionTokenResult<()>;
+    fn touch_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()>;
+    fn set_token_sellable(&mut self, t_token : & TransitionToken, amount : Option<f32>) -> SessionTokenResult<()>;
+    fn unset_token_sellable(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()>;
+    //
+    async fn reload_session_info(&mut self, session_token : & SessionToken, ownership_key : & Ucwid, hash_of_p2 : Hash) -> SessionTokenResult<()>;
+    async fn reload_token_info(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()>;
+    //
+    fn list_tranferable_tokens(&mut self, session_token : & SessionToken) -> SessionTokenResult<Vec<TransitionToken>>;
+    fn list_sellable_tokens(&mut self) -> Vec<TransitionToken>;
+    fn list_unassigned_tokens(&mut self) -> Vec<TransitionToken>;
+    fn list_detached_sessions(&mut self) -> Vec<SessionToken>;
+    fn list_pending_tokens(&mut self) -> Vec<TransitionToken>;
+}
+
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+pub struct LocalSessionTokens< D: DB + std::marker::Unpin > {
+    //
+    _db : D,
+    //
+    _session_to_owner : HashMap<SessionToken,Ucwid>,
+    _owner_to_sessions : HashMap<Ucwid,Vec<SessionToken>>,           // an owner's sessions, oldest first
+    _token_to_owner : HashMap<Token,Ucwid>,                         // map to owner -- token belongs to owner (Ucwid)
+    _token_to_session : HashMap<TransitionToken,SessionToken>,
+    _session_checking_tokens : HashMap<SessionToken,String>,
+    _token_to_information : HashMap<TransitionToken,String>,
+    _token_scopes : HashMap<TransitionToken,ScopeSet>,
+    _token_parent : HashMap<TransitionToken,TransitionToken>,               // derived token -> the token it was derived from
+    _token_children : HashMap<TransitionToken,HashSet<TransitionToken>>,
+    _sessions_to_their_tokens :  HashMap<SessionToken,SessionTokenSets>,
+    _detached_sessions : HashSet<SessionToken>,
+    _session_challenges : HashMap<SessionToken,(String,Millis)>,        // session -> (nonce, when it must be answered by)
+    _proven_sessions : HashSet<SessionToken>,
+    _rotated_sessions : HashMap<SessionToken,(SessionToken,Millis)>,    // old session -> (new session, end of grace period)
+    _orphaned_tokens : HashSet<TransitionToken>,
+    _transfer_proofs : HashMap<TransitionToken,Vec<TransferAuthorization>>,     // kept after the token is gone, as the record of who gave it to whom
+    _refresh_families : HashMap<TransitionToken,RefreshFamily>,
+    _spent_refresh_tokens : HashMap<TransitionToken,SessionToken>,       // rotated out refresh tokens, kept to catch replays
+    //
+    _session_timing : HashMap<SessionToken, SessionTimingInfo>,
+    _all_tranferable_tokens : HashMap<TransitionToken,TransferableTokenInfo>,
+    _token_timing : HashMap<TransitionToken,TokenTimingInfo>,
+    _session_deadlines : DeadlineQueue<SessionToken>,
+    _token_deadlines : DeadlineQueue<TransitionToken>,
+    _pending_tokens : HashSet<TransitionToken>,                     // tokens waiting for their not-before instant
+    _activation_deadlines : DeadlineQueue<TransitionToken>,
+    //
+    _token_creator : token_lambda,
+    _prefix_registry : PrefixRegistry,
+    _async_token_creator : Option<async_token_lambda>,
+    _token_signer : Option<TokenSigner>,
+    _caveat_root_key : Option<Vec<u8>>,
+    _caveat_ids : HashMap<String,TransitionToken>,                 // the opaque id a caveat token carries -> the token it stands for
+    _jwt_key : Option<JwtKey>,
+    _jwt_id_key : Vec<u8>,                                          // keys the opaque ids JWTs carry for sid and jti
+    _jwt_ids : HashMap<String,Token>,                               // opaque id -> the session or token it stands for
+    _clock : clock_box,
+    //
+    _general_session_timeout : i32,
+    _session_limit : SessionLimit,
+    _proof_required : bool,
+    _transfer_signing : TransferSigning,
+    _general_token_timeout : i32,
+}
+
+
+// the number of old deadline entries tolerated before the queues are compacted
+const DEADLINE_SLACK : usize = 64;
+
+
+// return_
+// helper function that clears out some trouble with ownership
+
+fn return_<S,T> (_t_to_thing : & HashMap::<S,T>,  tok : &S) -> Option<T> where S: Eq, S: std::hash::Hash, T: Clone {
+    _t_to_thing.get(tok).cloned()
+}
+
+
+// token_text
+// the string of either kind of token
+
+fn token_text(token : Token) -> String {
+    match token {
+        Token::SessionToken(st) => st,
+        Token::TransitionToken(tt) => tt,
+    }
+}
+
+
+
+
+#[async_trait]
+#[allow(clippy::unused_unit)]      // the methods keep the explicit unit returns of the trait, as in the other language defaults
+impl<D: DB + std::marker::Unpin> TokenTables<D> for LocalSessionTokens<D> {
+    type Jsonable = serde_json::Value;
+    //
+    fn new(db : D, token_creator : Option<token_lambda>) -> LocalSessionTokens<D> {
+        let s_to_o = HashMap::<SessionToken,Ucwid>::new();
+        let o_to_s = HashMap::<Ucwid,Vec<SessionToken>>::new();
+        let t_to_o = HashMap::<Token,Ucwid>::new();
+        let t_to_s = HashMap::<TransitionToken,SessionToken>::new();
+        let s_c_t = HashMap::<SessionToken,String>::new();
+        let t_to_i = HashMap::<TransitionToken,String>::new();
+        let s_to_t = HashMap::<SessionToken,SessionTokenSets>::new();
+        let d_s = HashSet::<SessionToken>::new();
+        let r_s = HashMap::<SessionToken,(SessionToken,Millis)>::new();
+        let o_t = HashSet::<TransitionToken>::new();
+        let r_f = HashMap::<TransitionToken,RefreshFamily>::new();
+        let s_r_t = HashMap::<TransitionToken,SessionToken>::new();
+        let s_t = HashMap::<SessionToken,SessionTimingInfo>::new();
+        let a_t_t = HashMap::<TransitionToken,TransferableTokenInfo>::new();
+        let t_t = HashMap::<TransitionToken,TokenTimingInfo>::new();
+        //
+        let tl : token_lambda = match token_creator {
+            Some(app_tl) => app_tl,
+            None => Box::new(default_token_maker)
+        };
+        let general_session_timeout = GENERAL_DEFAULT_SESSION_TIMEOUT;
+        //
+        //
+        LocalSessionTokens {
+            _db : db,
+            _session_to_owner : s_to_o,
+            _owner_to_sessions : o_to_s,
+            _token_to_owner : t_to_o,
+            _token_to_session : t_to_s,
+            _session_checking_tokens : s_c_t,
+            _token_to_information : t_to_i,
+            _token_scopes : HashMap::new(),
+            _token_parent : HashMap::new(),
+            _token_children : HashMap::new(),
+            _sessions_to_their_tokens :  s_to_t,
+            //
+            _detached_sessions : d_s,
+            _session_challenges : HashMap::new(),
+            _proven_sessions : HashSet::new(),
+            _rotated_sessions : r_s,
+            _orphaned_tokens : o_t,
+            _transfer_proofs : HashMap::new(),
+            _refresh_families : r_f,
+            _spent_refresh_tokens : s_r_t,
+            //
+            _session_timing : s_t,
+            _all_tranferable_tokens : a_t_t,
+            _token_timing : t_t,
+            _session_deadlines : DeadlineQueue::new(),
+            _token_deadlines : DeadlineQueue::new(),
+            _pending_tokens : HashSet::new(),
+            _activation_deadlines : DeadlineQueue::new(),
+        
+            _token_creator : tl,
+            _prefix_registry : PrefixRegistry::new(),
+            _async_token_creator : None,
+            _token_signer : None,
+            _caveat_root_key : None,
+            _caveat_ids : HashMap::new(),
+            _jwt_key : None,
+            _jwt_id_key : make_nonce().into_bytes(),
+            _jwt_ids : HashMap::new(),
+            _clock : Box::new(SystemClock),
+            _general_session_timeout : general_session_timeout,
+            _session_limit : SessionLimit::Unlimited,
+            _proof_required : false,
+            _transfer_signing : TransferSigning::Unsigned,
+            _general_token_timeout : i32::MAX,
+        }
+    }
+
+
+
+    // decrement_timers
+    // removes the sessions and tokens whose time has run out, as told by the clock
+    // only the entries that have come due in the deadline queues are looked at
+    // returns the number of entries removed, counting the tokens that went with their sessions
+
+    async fn decrement_timers(&mut self) -> usize {
+        let now = self._clock.now();
+        let entries_before = self._session_timing.len() + self._token_timing.len();
+        //
+        {
+            let mut to_destory = Vec::<SessionToken>::new();
+            for (at, sess_tok) in self._session_deadlines.due(now) {
+                if let Some(time_info) = self._session_timing.get(&sess_tok) {
+                    if time_info.expires_at() == at {       // otherwise the deadline has moved since it was queued
+                        to_destory.push(sess_tok);
+                    }
+                }
+            }
+            for sess_tok in to_destory {
+                self.remove_session(&sess_tok).await.ok();
+            }
+        }
+        {
+            let mut to_destory = Vec::<TransitionToken>::new();
+            for (at, t_tok) in self._token_deadlines.due(now) {
+                if let Some(time_info) = self._token_timing.get(&t_tok) {
+                    if time_info.expires_at() == at {
+                        to_destory.push(t_tok);
+                    }
+                }
+            }
+            for t_tok in to_destory {
+                self.destroy_token(&t_tok).await.ok();      // the token may have gone with its session
+            }
+        }
+        {
+            let ended : Vec<SessionToken> = self._rotated_sessions.iter().filter(|(_, (_, grace_ends))| *grace_ends <= now).map(|(old, _)| old.to_string()).collect();
+            for old_session in ended {
+                self._rotated_sessions.remove(&old_session);
+                self._db.del_session_key_value(&old_session).await.ok();
+                self._db.del_key_value(&old_session).await.ok();
+            }
+        }
+        for (at, t_tok) in self._activation_deadlines.due(now) {       // promote the tokens whose time has come
+            if self._token_timing.get(&t_tok).is_some_and(|time_info| time_info._not_before == at) {
+                self._pending_tokens.remove(&t_tok);
+            }
+        }
+        self.compact_deadlines();
+        entries_before - (self._session_timing.len() + self._token_timing.len())
+    }
+
+    fn set_token_creator(&mut self, token_creator : Option<token_lambda>) -> () {
+        match token_creator {
+            Some(app_tl) => {
+                self._token_creator = app_tl;
+            }
+            None => {
+                self._token_creator = Box::new(default_token_maker);
+            }
+        }
+    }
+
+    // set_async_token_creator
+    // used by create_token_async -- without one, create_token_async falls back to the token creator
+
+    fn set_async_token_creator(&mut self, token_creator : Option<async_token_lambda>) -> () {
+        self._async_token_creator = token_creator;
+    }
+
+    fn set_clock(&mut self, clock : Option<clock_box>) -> () {
+        match clock {
+            Some(app_clock) => {
+                self._clock = app_clock;
+            }
+            None => {
+                self._clock = Box::new(SystemClock);
+            }
+        }
+    }
+
+    // set_token_signer
+    // with a signer, created tokens carry an HMAC and tokens that do not verify are turned away without a lookup
+
+    fn set_token_signer(&mut self, signer : Option<TokenSigner>) -> () {
+        self._token_signer = signer;
+    }
+
+    // set_caveat_root_key
+    // the key caveat tokens are minted and verified with -- services that verify offline need the same key.
+    // Caveat tokens minted under an earlier key no longer resolve.
+
+    fn set_caveat_root_key(&mut self, root_key : Option<Vec<u8>>) -> () {
+        self._caveat_root_key = root_key;
+        self._caveat_ids.clear();
+    }
+
+    fn set_jwt_key(&mut self, key : Option<JwtKey>) -> () {
+        self._jwt_key = key;
+        self._jwt_ids.clear();
+    }
+
+    fn set_transfer_signing(&mut self, signing : TransferSigning) -> () {
+        self._transfer_signing = signing;
+    }
+
+    fn set_session_limit(&mut self, limit : SessionLimit) -> () {
+        self._session_limit = limit;
+    }
+
+    async fn add_session(&mut self, session_token : & SessionToken, ownership_key : & Ucwid, o_t_token : Option<TransitionToken>, shared :  Option<bool>) -> SessionTokenResult<AddedSession> {
+        let displaced = self.sessions_to_displace(session_token,ownership_key)?;
+        let hash_of_p2 = self._db.set_session_key_value(session_token,ownership_key.to_string()).await?;
+        for old_session in &displaced {         // only once the new session is in the DB, so a failed write costs the owner nothing
+            self.remove_session(old_session).await?;
+        }
+        if let Some(prev_owner) = self._session_to_owner.insert(session_token.to_string(),ownership_key.to_string()) {
+            self.unlist_session(&prev_owner,session_token);
+        }
+        self._owner_to_sessions.entry(ownership_key.to_string()).or_default().push(session_token.to_string());
+        self._session_checking_tokens.insert(session_token.to_string(),hash_of_p2.to_string());
+        let st = Token::SessionToken(session_token.to_string());
+        self._token_to_owner.insert(st,ownership_key.to_string());
+        let mut sess_token_set = SessionTokenSets::new();
+        //
+        if let Some(t_token) = o_t_token {
+            self._token_to_session.insert(t_token.to_string(), session_token.to_string());
+            sess_token_set.session_bounded.insert(t_token.to_string());
+            //
+            let tt = Token::TransitionToken(t_token.to_string());
+            self._token_to_owner.insert(tt,ownership_key.to_string());
+            let owk = StructOrString::TypeStr(hash_of_p2.to_string());
+            self.add_token(&t_token, owk).await?;
+        }
+        self._sessions_to_their_tokens.insert(session_token.to_string(),sess_token_set);
+        //
+        let mut sti = SessionTimingInfoBuilder::default()._started_at(self._clock.now())._time_allotted(self._general_session_timeout).build()?;
+        //
+        let result = match shared {
+            Some(share_chk) => {
+                if share_chk {
+                    sti._shared = true;
+                    let value = serde_json::to_string(&sti)?;
+                    self._db.set_key_value(session_token,value.as_str()).await?;
+                    Some(hash_of_p2)
+                } else {
+                    None
+                }
+            }
+            _ => None
+        };
+        //
+        self._session_deadlines.schedule(session_token.to_string(),sti.expires_at());
+        self._session_timing.insert(session_token.to_string(),sti);
+        Ok(AddedSession { _hash : result, _displaced : displaced })
+    }
+
+    async fn active_session(&self, session_token : & SessionToken, ownership_key : & Ucwid) -> SessionTokenResult<bool> {
+        //
+        if let Some((new_session, grace_ends)) = self._rotated_sessions.get(session_token) {     // a rotated session during its grace period
+            if self._clock.now() >= *grace_ends {
+                return Err(SessionTokenError::Expired(session_token.to_string()))
+            }
+            return self.active_session(new_session, ownership_key).await
+        }
+        if let Some(s_time_info) = self._session_timing.get(session_token) {
+            if s_time_info.is_expired(self._clock.now()) {
+                return Err(SessionTokenError::Expired(session_token.to_string()))
+            }
+        }
+        match self._session_checking_tokens.get(session_token) {
+            Some(hh_unidentified) => {
+                let hh_str : & str = hh_unidentified.as_str();
+                let truth = self._db.check_hash(hh_str,ownership_key).await?;
+                if truth && self._proof_required && !self._proven_sessions.contains(session_token) {
+                    return Err(SessionTokenError::NotProven(session_token.to_string()))
+                }
+                Ok(truth)
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+
+    async fn destroy_session(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()> {
+        //
+        let session_token = match return_::<TransitionToken,SessionToken>(& self._token_to_session,t_token) {
+            Some(st) => st,
+            _ => return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        };
+        self.remove_session(&session_token).await
+    }
+
+    // sessions_for_owner
+    // an owner may have several sessions at once (phone, laptop) -- they are listed oldest first
+
+    fn sessions_for_owner(&self, ownership_key : & Ucwid) -> Vec<SessionToken> {
+        match self._owner_to_sessions.get(ownership_key) {
+            Some(sessions) => sessions.clone(),
+            _ => vec![]
+        }
+    }
+
+    async fn destroy_all_sessions_for_owner(&mut self, ownership_key : & Ucwid) -> SessionTokenResult<usize> {
+        let sessions = self.sessions_for_owner(ownership_key);
+        for session_token in &sessions {
+            self.remove_session(session_token).await?;
+        }
+        Ok(sessions.len())
+    }
+
+
+    fn allow_session_detach(&mut self, session_token : SessionToken) -> SessionTokenResult<()> {
+        match self._session_timing.get_mut(&session_token) {
+            Some(s_time_info) => {
+                s_time_info._detachment_allowed = true;
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token))
+        }
+    }
+
+    async fn detach_session(&mut self, session_token : SessionToken) -> SessionTokenResult<()> {
+        match self._session_timing.get_mut(&session_token) {
+            Some(s_time_info) => {
+                s_time_info.detach(self._clock.now());
+                self._session_deadlines.schedule(session_token.to_string(),s_time_info.expires_at());
+                self._detached_sessions.insert(session_token.to_string());
+                if s_time_info._shared {
+                    let value = serde_json::to_string(&s_time_info)?;
+                    self._db.set_key_value(&session_token,value.as_str()).await?;
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token))
+        }
+    }
+
+    async fn attach_session(&mut self, session_token : SessionToken) -> SessionTokenResult<()> {
+        match self._session_timing.get_mut(&session_token) {
+            Some(s_time_info) => {
+                s_time_info.attach();
+                self._session_deadlines.schedule(session_token.to_string(),s_time_info.expires_at());
+                self._detached_sessions.remove(&session_token);
+                if s_time_info._shared {
+                    let value = serde_json::to_string(&s_time_info)?;
+                    self._db.set_key_value(&session_token,value.as_str()).await?;
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token))
+        }
+    }
+
+
+    // rotate_session
+    // gives the session a fresh token, keeping its owner, timing and tokens -- e.g. after a login or a step-up,
+    // so that a token known before the change in privilege is of no use after it.
+    // With a grace period, the old token stays good (for active_session) for that long.
+    // The DB is updated first, so that a DB failure leaves the tables as they were.
+
+    async fn rotate_session(&mut self, session_token : & SessionToken, grace_period : Option<i32>) -> SessionTokenResult<SessionToken> {
+        let now = self._clock.now();
+        let owner = match self._session_to_owner.get(session_token) {
+            Some(owner) => owner.to_string(),
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        let shared_value = match self._session_timing.get(session_token) {
+            Some(s_time_info) => {
+                if s_time_info.is_expired(now) {
+                    return Err(SessionTokenError::Expired(session_token.to_string()))
+                }
+                if s_time_info._shared { Some(serde_json::to_string(s_time_info)?) } else { None }
+            }
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        let new_session = token_text(self.create_token(Some(SESSION_PEFIX.to_string())));
+        //
+        let hash_of_p2 = self._db.set_session_key_value(&new_session,owner.to_string()).await?;
+        let in_grace = grace_period.is_some_and(|grace| grace > 0);     // the old records stay in the DB until the grace period ends
+        if let Some(value) = &shared_value {
+            if let Err(err) = self._db.set_key_value(&new_session,value.as_str()).await {
+                self._db.del_session_key_value(&new_session).await.ok();
+                return Err(err.into())
+            }
+            if !in_grace {
+                self._db.del_key_value(session_token).await?;
+            }
+        }
+        if !in_grace {
+            self._db.del_session_key_value(session_token).await?;
+        }
+        //
+        self._session_to_owner.remove(session_token);
+        self._session_to_owner.insert(new_session.to_string(),owner.to_string());
+        if let Some(sessions) = self._owner_to_sessions.get_mut(&owner) {
+            for st in sessions.iter_mut() {
+                if st == session_token {
+                    *st = new_session.to_string();     // the session keeps its place in the owner's list
+                }
+            }
+        }
+        self._session_checking_tokens.remove(session_token);
+        self._session_checking_tokens.insert(new_session.to_string(),hash_of_p2);
+        if let Some(owner_key) = self._token_to_owner.remove(&Token::SessionToken(session_token.to_string())) {
+            self._token_to_owner.insert(Token::SessionToken(new_session.to_string()),owner_key);
+        }
+        if let Some(token_sets) = self._sessions_to_their_tokens.remove(session_token) {
+            for t_token in token_sets.session_bounded.iter().chain(token_sets.session_carries.iter()) {
+                self._token_to_session.insert(t_token.to_string(),new_session.to_string());
+            }
+            self._sessions_to_their_tokens.insert(new_session.to_string(),token_sets);
+        }
+        if self._detached_sessions.remove(session_token) {
+            self._detached_sessions.insert(new_session.to_string());
+        }
+        if self._proven_sessions.remove(session_token) {
+            self._proven_sessions.insert(new_session.to_string());
+        }
+        self._session_challenges.remove(session_token);
+        for family in self._refresh_families.values_mut() {        // refresh tokens follow their session to its new token
+            if &family._session == session_token {
+                family._session = new_session.to_string();
+            }
+        }
+        for spent_session in self._spent_refresh_tokens.values_mut() {     // so that a replay still ends the session
+            if spent_session == session_token {
+                *spent_session = new_session.to_string();
+            }
+        }
+        if let Some(s_time_info) = self._session_timing.remove(session_token) {
+            self._session_deadlines.schedule(new_session.to_string(),s_time_info.expires_at());
+            self._session_timing.insert(new_session.to_string(),s_time_info);
+        }
+        for (_, (target, _)) in self._rotated_sessions.iter_mut() {      // earlier tokens still in their grace period follow along
+            if target == session_token {
+                *target = new_session.to_string();
+            }
+        }
+        if let Some(grace) = grace_period.filter(|grace| *grace > 0) {
+            self._rotated_sessions.insert(session_token.to_string(),(new_session.to_string(),now.saturating_add(grace as Millis)));
+        }
+        Ok(new_session)
+    }
+
+
+    // require_proof_of_possession
+    // when required, a session is only active once its owner has signed a challenge with the key the Ucwid wraps
+
+    fn require_proof_of_possession(&mut self, required : bool) -> () {
+        self._proof_required = required;
+    }
+
+    // issue_session_challenge
+    // a fresh nonce for the session, to be signed (see challenge_message) and given back within CHALLENGE_TIMEOUT
+
+    fn issue_session_challenge(&mut self, session_token : & SessionToken) -> SessionTokenResult<String> {
+        if !self._session_to_owner.contains_key(session_token) {
+            return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+        let nonce = make_nonce();
+        let answer_by = self._clock.now().saturating_add(CHALLENGE_TIMEOUT as Millis);
+        self._session_challenges.insert(session_token.to_string(),(nonce.clone(),answer_by));
+        Ok(nonce)
+    }
+
+    // answer_session_challenge
+    // a challenge may be answered once -- it is spent whether or not the signature verifies
+
+    fn answer_session_challenge(&mut self, session_token : & SessionToken, signature : &[u8]) -> SessionTokenResult<()> {
+        let owner = match self._session_to_owner.get(session_token) {
+            Some(owner) => owner,
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        let nonce = match self._session_challenges.remove(session_token) {
+            Some((nonce, answer_by)) if self._clock.now() < answer_by => nonce,
+            _ => return Err(SessionTokenError::NotProven(session_token.to_string()))
+        };
+        let key = match UcwidKey::from_ucwid(owner) {
+            Some(key) => key,
+            _ => return Err(SessionTokenError::NotProven(session_token.to_string()))
+        };
+        if !key.verify(&challenge_message(session_token,&nonce),signature) {
+            return Err(SessionTokenError::Forged(session_token.to_string()))
+        }
+        self._proven_sessions.insert(session_token.to_string());
+        Ok(())
+    }
+
+
+    // ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+    // register_token_prefix
+    // tokens made or added with the prefix take on the kind, timeout, transferability and sellability of the policy
+
+    fn register_token_prefix(&mut self, prefix : &str, policy : TokenPolicy) -> () {
+        self._prefix_registry.register(prefix,policy);
+    }
+
+    fn classify_token(&self, token : &str) -> Option<TokenPolicy> {
+        self._prefix_registry.classify(token).map(|(_, policy)| policy.clone())
+    }
+
+    fn create_token(&self, prefix : Option<String> ) -> Token {
+        let token = self._token_creator.generate(prefix.as_deref());
+        self.sign_token(self._prefix_registry.apply_kind(token))
+    }
+
+    async fn create_token_async(&self, prefix : Option<String> ) -> Token {
+        let token = match &self._async_token_creator {
+            Some(creator) => creator.generate(prefix.as_deref()).await,
+            _ => self._token_creator.generate(prefix.as_deref())
+        };
+        self.sign_token(self._prefix_registry.apply_kind(token))
+    }
+
+    // mint_caveat_token
+    // a caveat token for a token in the tables, carrying what the tables know of it:
+    // its owner, its session (when bounded to one), its expiry, and its scopes (when it has any).
+    // The token itself is not in it -- the caveat token carries an opaque id that only these tables map back to the token.
+    // The holder may add caveats of its own before passing the token on.
+
+    fn mint_caveat_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<CaveatToken> {
+        let root_key = match &self._caveat_root_key {
+            Some(root_key) => root_key,
+            _ => return Err(SessionTokenError::NoRootKey)
+        };
+        let time_info = match self._token_timing.get(t_token) {
+            Some(time_info) => time_info,
+            _ => return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        };
+        let id = opaque_id(root_key,"token",t_token);
+        let mut caveat_token = CaveatToken::mint(root_key,&id).restrict(Caveat::ExpiresAt(time_info.expires_at()))?;
+        if let Some(owner) = self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())) {
+            caveat_token = caveat_token.restrict(Caveat::owner(root_key,owner))?;
+        }
+        if let Some(session_token) = self._token_to_session.get(t_token) {
+            caveat_token = caveat_token.restrict(Caveat::session(root_key,session_token))?;
+        }
+        if let Some(scopes) = self._token_scopes.get(t_token) {
+            caveat_token = caveat_token.restrict(Caveat::Scopes(scopes.clone()))?;
+        }
+        self._caveat_ids.insert(id,t_token.to_string());
+        Ok(caveat_token)
+    }
+
+    // verify_caveat_token
+    // the online check: the caveats are checked against the tables' current owner and session for the token,
+    // so a token destroyed or moved since the caveat token was minted no longer verifies
+
+    fn verify_caveat_token(&self, encoded : &str, scope : Option<&str>) -> SessionTokenResult<TransitionToken> {
+        let root_key = match &self._caveat_root_key {
+            Some(root_key) => root_key,
+            _ => return Err(SessionTokenError::NoRootKey)
+        };
+        let caveat_token = CaveatToken::decode(encoded)?;
+        let t_token = match self._caveat_ids.get(&caveat_token._id) {
+            Some(t_token) => t_token,
+            _ => return Err(SessionTokenError::UnknownToken(caveat_token._id.to_string()))
+        };
+        self.check_signature(t_token)?;
+        self.check_token_live(t_token)?;
+        let context = CaveatContextBuilder::default()
+                            ._now(self._clock.now())
+                            ._scope(scope.map(|scope| scope.to_string()))
+                            ._owner(self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())).cloned())
+                            ._session(self._token_to_session.get(t_token).cloned())
+                            .build()?;
+        caveat_token.verify(root_key,&context)?;
+        Ok(t_token.to_string())
+    }
+
+    // session_jwt
+    // a JWT for downstream services, naming the session's owner and expiring when the session would.
+    // The session is named by an opaque id (`sid`) that only these tables map back to it.
+
+    fn session_jwt(&mut self, session_token : & SessionToken) -> SessionTokenResult<String> {
+        if self._jwt_key.is_none() {
+            return Err(SessionTokenError::NoSigningKey)
+        }
+        let expires_at = match self._session_timing.get(session_token) {
+            Some(time_info) => time_info.expires_at(),
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        let claims = JwtClaims {
+            _owner : self._session_to_owner.get(session_token).cloned(),
+            _session : Some(self.jwt_id(Token::SessionToken(session_token.to_string()))),
+            _token : None,
+            _expires : expires_at / 1000,
+            _issued_at : self._clock.now() / 1000,
+            _scope : None,
+        };
+        match &self._jwt_key {
+            Some(key) => encode_jwt(&claims,key),
+            _ => Err(SessionTokenError::NoSigningKey)
+        }
+    }
+
+    // token_jwt
+    // a JWT for a token -- its owner, its session when bounded to one, and its scopes.
+    // The token and session are named by opaque ids (`jti`, `sid`) as in session_jwt.
+
+    fn token_jwt(&mut self, t_token : & TransitionToken) -> SessionTokenResult<String> {
+        if self._jwt_key.is_none() {
+            return Err(SessionTokenError::NoSigningKey)
+        }
+        let expires_at = match self._token_timing.get(t_token) {
+            Some(time_info) => time_info.expires_at(),
+            _ => return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        };
+        let scope = self._token_scopes.get(t_token).map(|scopes| scopes.iter().cloned().collect::<Vec<String>>().join(" "));
+        let session = self._token_to_session.get(t_token).cloned();
+        let claims = JwtClaims {
+            _owner : self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())).cloned(),
+            _session : session.map(|session_token| self.jwt_id(Token::SessionToken(session_token))),
+            _token : Some(self.jwt_id(Token::TransitionToken(t_token.to_string()))),
+            _expires : expires_at / 1000,
+            _issued_at : self._clock.now() / 1000,
+            _scope : scope,
+        };
+        match &self._jwt_key {
+            Some(key) => encode_jwt(&claims,key),
+            _ => Err(SessionTokenError::NoSigningKey)
+        }
+    }
+
+    // verify_jwt
+    // besides the signature and expiry, the session or token named has to still be live in the tables with the same owner,
+    // so that destroying it revokes the JWTs issued for it, and a token waiting for its not-before instant is not let in early.
+    // The claims are returned as issued, with the opaque ids.
+
+    fn verify_jwt(&self, jwt : &str) -> SessionTokenResult<JwtClaims> {
+        let key = match &self._jwt_key {
+            Some(key) => key,
+            _ => return Err(SessionTokenError::NoSigningKey)
+        };
+        let claims = decode_jwt(jwt,key,self._clock.now())?;
+        match (&claims._token, &claims._session) {
+            (Some(jti), _) => {
+                let t_token = match self._jwt_ids.get(jti) {
+                    Some(Token::TransitionToken(t_token)) => t_token,
+                    _ => return Err(SessionTokenError::UnknownToken(jti.to_string()))
+                };
+                self.check_token_live(t_token)?;
+                if self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())) != claims._owner.as_ref() {
+                    return Err(SessionTokenError::UnknownToken(jti.to_string()))
+                }
+            }
+            (_, Some(sid)) => {
+                let session_token = match self._jwt_ids.get(sid) {
+                    Some(Token::SessionToken(session_token)) => session_token,
+                    _ => return Err(SessionTokenError::UnknownSession(sid.to_string()))
+                };
+                let owner = self._session_to_owner.get(session_token);
+                match self._session_timing.get(session_token) {
+                    Some(time_info) if owner == claims._owner.as_ref() => {
+                        if time_info.is_expired(self._clock.now()) {
+                            return Err(SessionTokenError::Expired(sid.to_string()))
+                        }
+                    }
+                    _ => return Err(SessionTokenError::UnknownSession(sid.to_string()))
+                }
+            }
+            _ => return Err(SessionTokenError::Forged(jwt.to_string()))
+        }
+        Ok(claims)
+    }
+
+    async fn add_token(&mut self, t_token : & TransitionToken, value : StructOrString<Self::Jsonable> ) -> SessionTokenResult<()> {
+        //
+        let tval : String = match value {
+            StructOrString::TypeStr(sval) => sval,
+            StructOrString::TypeGen(struct_val) => struct_val.to_string()
+        };
+
+        self._db.set_key_value(t_token,tval.as_str()).await?;
+        self._token_to_information.insert(t_token.to_string(),tval);
+
+        let mut tt_info = TokenTimingInfoBuilder::default()._started_at(self._clock.now()).build()?;
+        //
+        if let Some((_, policy)) = self._prefix_registry.classify(t_token) {
+            if let Some(timeout) = policy._timeout {
+                tt_info._time_allotted = timeout;
+            }
+            if policy._transferable && !self._all_tranferable_tokens.contains_key(t_token) {
+                if let Some(owner) = self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())) {
+                    let tf_info = TransferableTokenInfoBuilder::default()._owner(owner.to_string()).build()?;
+                    self._all_tranferable_tokens.insert(t_token.to_string(),tf_info);
+                }
+            }
+            if policy._sellable {
+                if let Some(tf_info) = self._all_tranferable_tokens.get_mut(t_token) {
+                    tf_info._sellable = true;
+                }
+            }
+        }
+        self._token_deadlines.schedule(t_token.to_string(),tt_info.expires_at());
+        self._token_timing.insert(t_token.to_string(),tt_info);
+        Ok(())
+    }
+
+
+
+    async fn transition_token_is_active(&mut self, token : & TransitionToken) -> SessionTokenResult<String> {
+        self.check_signature(token)?;
+        if let Some(time_info) = self._token_timing.get(token) {
+            let now = self._clock.now();
+            if time_info.is_expired(now) {
+                return Err(SessionTokenError::Expired(token.to_string()))
+            }
+            if time_info.is_pending(now) {
+                return Err(SessionTokenError::NotYetActive(token.to_string()))
+            }
+        }
+        match self._token_to_information.get(token) {
+            Some(value) => {
+                Ok(value.to_string())
+            }
+            _ => {
+                match self._db.get_key_value(token).await? {
+                    Some(db_val) => {
+                        let sval : String = db_val;
+                        self.add_token(token,StructOrString::TypeStr(sval.clone())).await?;
+                        Ok(sval)
+                    }
+                    _ => Err(SessionTokenError::UnknownToken(token.to_string()))
+                }
+            }
+        }
+    }
+
+
+    async fn destroy_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()> {
+        //
+        if !self._token_to_information.contains_key(t_token) && !self._token_timing.contains_key(t_token) {
+            return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+        let t = t_token.clone();
+        if let Some(session_token) = self._token_to_session.get(&t) {
+            if let Some(sess_token_set) = self._sessions_to_their_tokens.get_mut(session_token) {
+                //
+                sess_token_set.session_bounded.remove(t_token);
+                sess_token_set.session_carries.remove(t_token);
+                //
+            }
+        }
+        //
+        {
+            self._token_to_information.remove(t_token);
+            self._token_scopes.remove(t_token);
+            let t : Token = Token::TransitionToken(t_token.to_string());
+            self._token_to_owner.remove(&t);        // This map use the more generic Token enumerated type
+            self._orphaned_tokens.remove(t_token);
+            self._pending_tokens.remove(t_token);
+            self._refresh_families.remove(t_token);
+            self._token_timing.remove(t_token);
+            self._all_tranferable_tokens.remove(t_token);
+            self._token_to_session.remove(t_token);
+            if let Some(root_key) = &self._caveat_root_key {
+                self._caveat_ids.remove(&opaque_id(root_key,"token",t_token));
+            }
+            self._jwt_ids.remove(&opaque_id(&self._jwt_id_key,"token",t_token));
+            //
+            let t = t_token.clone();
+            self._db.del_key_value(&t).await?;
+        }
+        //
+        if let Some(parent) = self._token_parent.remove(t_token) {
+            if let Some(siblings) = self._token_children.get_mut(&parent) {
+                siblings.remove(t_token);
+            }
+        }
+        if let Some(children) = self._token_children.remove(t_token) {      // derived tokens go with the token they came from
+            for child in children {
+                self.destroy_token(&child).await.ok();
+            }
+        }
+        Ok(())
+    }
+
+
+    // derive_token
+    // a limited copy of a token to hand to a third party -- same owner, session and value, narrower scopes, no longer life.
+    // Destroying the parent destroys the derived token.
+    // A token with limited uses is not derived from, since the derived token's uses would come on top of the parent's.
+
+    async fn derive_token(&mut self, parent : & TransitionToken, restrictions : TokenRestrictions) -> SessionTokenResult<TransitionToken> {
+        let value = self.transition_token_is_active(parent).await?;
+        if self._token_timing.get(parent).is_some_and(|time_info| time_info._uses_left.is_some()) {
+            return Err(SessionTokenError::LimitedUse(parent.to_string()))
+        }
+        let held = self._token_scopes.get(parent).cloned().unwrap_or_default();
+        let scopes = match &restrictions._scopes {
+            Some(requested) => match narrow_scopes(&held,requested) {
+                Some(narrowed) => narrowed,
+                _ => return Err(SessionTokenError::OutOfScope(parent.to_string()))
+            },
+            _ => held
+        };
+        let parent_left = self.get_token_time_left(parent)?;
+        let timeout = match restrictions._timeout {
+            Some(timeout) => timeout.min(parent_left),
+            _ => parent_left
+        };
+        //
+        let child = token_text(self.create_token(restrictions._prefix.clone()));
+        self.add_token(&child,StructOrString::TypeStr(value)).await?;
+        self.set_token_timeout(&child,timeout)?;
+        self._token_scopes.insert(child.to_string(),scopes);
+        if let Some(session_token) = self._token_to_session.get(parent).cloned() {
+            self._token_to_session.insert(child.to_string(),session_token.to_string());
+            if let Some(sess_token_set) = self._sessions_to_their_tokens.get_mut(&session_token) {
+                sess_token_set.session_bounded.insert(child.to_string());
+            }
+        }
+        if let Some(owner) = self._token_to_owner.get(&Token::TransitionToken(parent.to_string())).cloned() {
+            self._token_to_owner.insert(Token::TransitionToken(child.to_string()),owner);
+        }
+        self._token_parent.insert(child.to_string(),parent.to_string());
+        self._token_children.entry(parent.to_string()).or_default().insert(child.to_string());
+        Ok(child)
+    }
+
+    fn derived_tokens(&self, parent : & TransitionToken) -> Vec<TransitionToken> {
+        match self._token_children.get(parent) {
+            Some(children) => children.iter().cloned().collect(),
+            _ => vec![]
+        }
+    }
+
+
+    // set_token_uses
+    // limits the number of times the token may be consumed -- the count is kept in the DB so that processes share it
+
+    async fn set_token_uses(&mut self, t_token : & TransitionToken, uses : u32) -> SessionTokenResult<()> {
+        if !self._token_timing.contains_key(t_token) {
+            return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+        self._db.set_use_count(t_token,uses).await?;
+        if let Some(time_info) = self._token_timing.get_mut(t_token) {
+            time_info._uses_left = Some(uses);
+        }
+        Ok(())
+    }
+
+    // consume_token
+    // uses the token once, returning its value -- a token is destroyed when its last use is taken.
+    // The DB takes the use, so that two processes cannot both take the last one.
+
+    async fn consume_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<String> {
+        let value = self.transition_token_is_active(t_token).await?;
+        match self._db.take_use(t_token).await? {
+            TokenUse::Unlimited => Ok(value),
+            TokenUse::Taken(uses_left) => {
+                if uses_left == 0 {
+                    self.destroy_token(t_token).await?;
+                } else if let Some(time_info) = self._token_timing.get_mut(t_token) {
+                    time_info._uses_left = Some(uses_left);
+                }
+                Ok(value)
+            }
+            TokenUse::Refused => {
+                self.destroy_token(t_token).await.ok();
+                Err(SessionTokenError::UsedUp(t_token.to_string()))
+            }
+        }
+    }
+
+    // issue_token_pair
+    // an access token and a refresh token, both bound to the session
+
+    async fn issue_token_pair(&mut self, session_token : & SessionToken, access_timeout : i32, refresh_timeout : i32) -> SessionTokenResult<TokenPair> {
+        match self._session_timing.get(session_token) {
+            Some(s_time_info) => {
+                if s_time_info.is_expired(self._clock.now()) {
+                    return Err(SessionTokenError::Expired(session_token.to_string()))
+                }
+            }
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+        let family = RefreshFamily { _session : session_token.to_string(), _access_timeout : access_timeout, _refresh_timeout : refresh_timeout };
+        self.issue_from_family(family, true).await
+    }
+
+    // redeem_refresh_token
+    // yields a new access token -- with rotate, the refresh token is spent and a new one takes its place.
+    // A spent refresh token coming back means that it has been copied, so the whole session is ended.
+
+    async fn redeem_refresh_token(&mut self, refresh_token : & TransitionToken, rotate : bool) -> SessionTokenResult<TokenPair> {
+        self.check_signature(refresh_token)?;
+        if let Some(session_token) = self._spent_refresh_tokens.get(refresh_token).cloned() {
+            self.remove_session(&session_token).await?;
+            return Err(SessionTokenError::Replayed(refresh_token.to_string()))
+        }
+        let family = match self._refresh_families.get(refresh_token) {
+            Some(family) => family.clone(),
+            _ => return Err(SessionTokenError::UnknownToken(refresh_token.to_string()))
+        };
+        if let Some(time_info) = self._token_timing.get(refresh_token) {
+            if time_info.is_expired(self._clock.now()) {
+                return Err(SessionTokenError::Expired(refresh_token.to_string()))
+            }
+        }
+        if !rotate {
+            let mut pair = self.issue_from_family(family, false).await?;
+            pair._refresh = refresh_token.to_string();
+            return Ok(pair)
+        }
+        let pair = self.issue_from_family(family.clone(), true).await?;
+        self.destroy_token(refresh_token).await?;
+        self._spent_refresh_tokens.insert(refresh_token.to_string(),family._session);
+        Ok(pair)
+    }
+
+
+    fn from_token(&self, token : TransitionToken) -> SessionTokenResult<Ucwid> {
+        let t = Token::TransitionToken(token.clone());
+        match self._token_to_owner.get(&t) {
+            Some(ucwid) => Ok(ucwid.to_owned()),
+            _ => Err(SessionTokenError::UnknownToken(token))
+        }
+    }
+
+
+    async fn add_session_bounded_token(&mut self, t_token : & TransitionToken, value : StructOrString<Self::Jsonable>, ownership_key : & Ucwid )  -> SessionTokenResult<()> {
+        let session_token = match self.receiving_session(ownership_key) {
+            Some(session_token) => session_token,
+            _ => return Err(SessionTokenError::UnknownOwner(ownership_key.to_string()))
+        };
+        match self._sessions_to_their_tokens.get_mut(&session_token) {
+            Some(sess_token_set) => {
+                sess_token_set.session_bounded.insert(t_token.to_string());
+                //
+                self._token_to_session.insert(t_token.to_string(), session_token.to_string());
+                let tt_info = TransferableTokenInfoBuilder::default()._owner(ownership_key.to_string()).build()?;
+                self._all_tranferable_tokens.insert(t_token.to_string(),tt_info);
+                self.add_token(t_token,value).await
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token))
+        }
+    }
+
+
+    async fn add_transferable_token(&mut self,  t_token : & TransitionToken, value : StructOrString<Self::Jsonable>, ownership_key : & Ucwid ) -> SessionTokenResult<()> {
+        let sst = match self.receiving_session(ownership_key) {
+            Some(session_token) => session_token,
+            _ => return Err(SessionTokenError::UnknownOwner(ownership_key.to_string()))
+        };
+        self.carry_token(t_token, value, ownership_key, sst).await
+    }
+
+    //      token_is_transferable
+    //
+    fn token_is_transferable(&self, t_token : &TransitionToken) -> bool {
+        if let Some(_ttok) = self._all_tranferable_tokens.get(t_token) {
+            return true
+        }
+        false
+    }
+
+
+    //      set_token_scopes
+    //
+    fn set_token_scopes(&mut self, t_token : & TransitionToken, scopes : ScopeSet) -> SessionTokenResult<()> {
+        if !self._token_timing.contains_key(t_token) {
+            return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+        self._token_scopes.insert(t_token.to_string(),scopes);
+        Ok(())
+    }
+
+    //      narrow_token_scopes
+    //      scopes may only be taken away -- asking to keep a scope the token does not hold is an error
+    //
+    fn narrow_token_scopes(&mut self, t_token : & TransitionToken, keep : & ScopeSet) -> SessionTokenResult<()> {
+        let held = self.token_scopes(t_token)?;
+        match narrow_scopes(&held,keep) {
+            Some(narrowed) => {
+                self._token_scopes.insert(t_token.to_string(),narrowed);
+                Ok(())
+            }
+            _ => Err(SessionTokenError::OutOfScope(t_token.to_string()))
+        }
+    }
+
+    fn token_scopes(&self, t_token : & TransitionToken) -> SessionTokenResult<ScopeSet> {
+        if !self._token_timing.contains_key(t_token) {
+            return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+        Ok(self._token_scopes.get(t_token).cloned().unwrap_or_default())
+    }
+
+    //      check_token_scope
+    //      for handlers authorizing an action -- an expired or pending token holds no scopes
+    //
+    fn check_token_scope(&self, t_token : & TransitionToken, scope : &str) -> SessionTokenResult<bool> {
+        self.check_signature(t_token)?;
+        self.check_token_live(t_token)?;
+        Ok(self._token_scopes.get(t_token).is_some_and(|scopes| scopes.contains(scope)))
+    }
+
+
+    //      acquire_token
+    //
+    async fn acquire_token(&mut self, t_token : & TransitionToken, session_token : & SessionToken, owner : & Ucwid) -> SessionTokenResult<()> {
+        if self._session_to_owner.get(session_token) != Some(owner) {
+            return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+        let value = self.transition_token_is_active(t_token).await?;
+        self.carry_token(t_token,StructOrString::TypeStr(value),owner,session_token.to_string()).await
+    }
+
+
+    //      transfer_token
+    //
+    async fn transfer_token(&mut self,  t_token : & TransitionToken, yielder_key : & Ucwid,  receiver_key : & Ucwid ) -> SessionTokenResult<()> {
+        if self._transfer_signing != TransferSigning::Unsigned {
+            return Err(SessionTokenError::Unauthorized(t_token.to_string()))
+        }
+        self.move_token(t_token,yielder_key,receiver_key).await
+    }
+
+    //      transfer_token_signed
+    //      the authorization is checked before anything in the tables changes, and kept once the token has moved.
+    //      An authorization is spent by what was signed, not by the signature bytes, which may be encoded more than one way.
+    //
+    async fn transfer_token_signed(&mut self, authorization : TransferAuthorization) -> SessionTokenResult<()> {
+        let t_token = authorization._token.to_string();
+        authorization.verify(self._transfer_signing)?;
+        let now = self._clock.now();
+        let window = TRANSFER_AUTHORIZATION_WINDOW as Millis;
+        if authorization._issued_at.saturating_add(window) <= now || authorization._issued_at > now.saturating_add(window) {
+            return Err(SessionTokenError::Expired(t_token))
+        }
+        let signed = authorization.signing_message();
+        if self._transfer_proofs.get(&t_token).is_some_and(|proofs| proofs.iter().any(|proof| proof.signing_message() == signed)) {
+            return Err(SessionTokenError::Replayed(t_token))
+        }
+        self.move_token(&t_token,&authorization._yielder,&authorization._receiver).await?;
+        self._transfer_proofs.entry(t_token).or_default().push(authorization);
+        Ok(())
+    }
+
+    fn transfer_proofs(&self, t_token : & TransitionToken) -> Vec<TransferAuthorization> {
+        self._transfer_proofs.get(t_token).cloned().unwrap_or_default()
+    }
+
+    // ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+
+    fn set_general_session_timeout(&mut self, timeout : i32) -> () {
+        self._general_session_timeout = timeout
+    }
+
+    async fn set_session_timeout(&mut self, session_token : & SessionToken, timeout : i32) -> SessionTokenResult<()> {
+        match self._session_timing.get_mut(session_token) {
+            Some(s_time_info) => {
+                s_time_info.restart(self._clock.now(),timeout);
+                self._session_deadlines.schedule(session_token.to_string(),s_time_info.expires_at());
+                if s_time_info._shared {
+                    let value = serde_json::to_string(s_time_info)?;
+                    self._db.set_key_value(session_token,value.as_str()).await?;
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+
+    fn get_session_timeout(&mut self, session_token : & SessionToken) -> SessionTokenResult<i32> {
+        match self._session_timing.get(session_token) {
+            Some(s_time_info) => Ok(s_time_info._time_allotted),
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+    fn get_session_time_left(&mut self, session_token : & SessionToken) -> SessionTokenResult<i32> {
+        match self._session_timing.get(session_token) {
+            Some(s_time_info) => Ok(s_time_info.time_left(self._clock.now())),
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+    // set_session_idle_timeout
+    // a session with an idle timeout runs out when it has not been touched for that long,
+    // or when its absolute time allotted runs out, whichever comes first -- None takes the idle timeout away
+
+    async fn set_session_idle_timeout(&mut self, session_token : & SessionToken, idle_timeout : Option<i32>) -> SessionTokenResult<()> {
+        match self._session_timing.get_mut(session_token) {
+            Some(s_time_info) => {
+                s_time_info._idle_allotted = idle_timeout;
+                s_time_info.touch(self._clock.now());
+                self._session_deadlines.schedule(session_token.to_string(),s_time_info.expires_at());
+                if s_time_info._shared {
+                    let value = serde_json::to_string(s_time_info)?;
+                    self._db.set_key_value(session_token,value.as_str()).await?;
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+    // touch_session
+    // keeps a session alive while it is in use -- the idle timeout starts over, the absolute one does not
+
+    async fn touch_session(&mut self, session_token : & SessionToken) -> SessionTokenResult<()> {
+        let now = self._clock.now();
+        match self._session_timing.get_mut(session_token) {
+            Some(s_time_info) => {
+                if s_time_info.is_expired(now) {
+                    return Err(SessionTokenError::Expired(session_token.to_string()))
+                }
+                s_time_info.touch(now);
+                self._session_deadlines.schedule(session_token.to_string(),s_time_info.expires_at());
+                if s_time_info._shared {
+                    let value = serde_json::to_string(s_time_info)?;
+                    self._db.set_key_value(session_token,value.as_str()).await?;
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+    }
+
+    //
+    fn set_general_token_timeout(&mut self, timeout : i32) -> () {
+        self._general_token_timeout = timeout;
+    }
+
+    fn set_disownment_token_timeout(&mut self, t_token : & TransitionToken, timeout : i32) -> SessionTokenResult<()> {
+        match self._token_timing.get_mut(t_token) {
+            Some(time_info) => {
+                time_info._time_allotted_after_detachment = timeout;
+                self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    fn set_token_timeout(&mut self, t_token : & TransitionToken,timeout : i32) -> SessionTokenResult<()> {
+        match self._token_timing.get_mut(t_token) {
+            Some(time_info) => {
+                let starts_at = self._clock.now().max(time_info._not_before);     // a pending token's time counts from its not-before instant
+                time_info.restart(starts_at,timeout);
+                self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    fn get_token_timeout(&mut self, t_token : & TransitionToken)  ->  SessionTokenResult<i32> {
+        match self._token_timing.get(t_token) {
+            Some(time_info) => Ok(time_info._time_allotted),
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    fn get_token_time_left(&mut self, t_token : & TransitionToken)  ->  SessionTokenResult<i32> {
+        match self._token_timing.get(t_token) {
+            Some(time_info) => Ok(time_info.time_left(self._clock.now())),
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    fn set_token_idle_timeout(&mut self, t_token : & TransitionToken, idle_timeout : Option<i32>) -> SessionTokenResult<()> {
+        match self._token_timing.get_mut(t_token) {
+            Some(time_info) => {
+                time_info._idle_allotted = idle_timeout;
+                time_info.touch(self._clock.now());
+                self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    fn touch_token(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()> {
+        let now = self._clock.now();
+        match self._token_timing.get_mut(t_token) {
+            Some(time_info) => {
+                if time_info.is_expired(now) {
+                    return Err(SessionTokenError::Expired(t_token.to_string()))
+                }
+                time_info.touch(now);
+                self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+
+    // set_token_not_before
+    // the token stays inactive until the instant given (a release embargo, a timed ticket),
+    // and its time allotted counts from then
+
+    fn set_token_not_before(&mut self, t_token : & TransitionToken, not_before : Millis) -> SessionTokenResult<()> {
+        match self._token_timing.get_mut(t_token) {
+            Some(time_info) => {
+                let now = self._clock.now();
+                if not_before > now {       // an instant already past leaves the deadline where it is
+                    let allotted = time_info._time_allotted;
+                    time_info.restart(not_before,allotted);
+                }
+                time_info._not_before = not_before;
+                self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+                if not_before > now {
+                    self._pending_tokens.insert(t_token.to_string());
+                    self._activation_deadlines.schedule(t_token.to_string(),not_before);
+                } else {
+                    self._pending_tokens.remove(t_token);
+                }
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+
+    fn set_token_sellable(&mut self, t_token : & TransitionToken, amount : Option<f32>) -> SessionTokenResult<()> {
+        match self._all_tranferable_tokens.get_mut(t_token) {
+            Some(tinf) => {
+                if let Some(amt) = amount {
+                    tinf._price = amt;
+                }
+                tinf._sellable = true;
+                Ok(())
+            }
+            _ => Err(SessionTokenError::NotTransferable(t_token.to_string()))
+        }
+    }
+
+
+    fn unset_token_sellable(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()> {
+        match self._all_tranferable_tokens.get_mut(t_token) {
+            Some(tinf) => {
+                tinf._sellable = false;
+                Ok(())
+            }
+            _ => Err(SessionTokenError::NotTransferable(t_token.to_string()))
+        }
+    }
+
+
+    //
+    async fn reload_session_info(&mut self, session_token : & SessionToken, ownership_key : & Ucwid, hash_of_p2 : Hash) -> SessionTokenResult<()> {
+        let data = match self._db.get_key_value(session_token).await? {
+            Some(data) => data,
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        if !self.active_session(session_token, ownership_key).await? {
+            return Err(SessionTokenError::NotActive(session_token.to_string()))
+        }
+        let stored_info : Value = serde_json::from_str(&data)?;
+        let mut s_info = SessionTimingInfoBuilder::default().build()?;
+        s_info.set_all(stored_info)?;
+        self._session_deadlines.schedule(session_token.to_string(),s_info.expires_at());
+        self._session_timing.insert(session_token.to_string(),s_info);
+        self._session_checking_tokens.insert(session_token.to_string(),hash_of_p2);
+        Ok(())
+    }
+
+
+    async fn reload_token_info(&mut self, t_token : & TransitionToken) -> SessionTokenResult<()> {    // promise
+        self.check_signature(t_token)?;
+        let data = match self._db.get_key_value(t_token).await? {
+            Some(data) => data,
+            _ => return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        };
+        let stored_info : Value = serde_json::from_str(&data)?;
+        let mut t_info = TokenTimingInfoBuilder::default().build()?;
+        t_info.set_all(stored_info)?;
+        self._token_deadlines.schedule(t_token.to_string(),t_info.expires_at());
+        self._token_timing.insert(t_token.to_string(),t_info);
+
+        future::ready(()).await;
+        Ok(())
+    }
+
+
+    //
+    fn list_tranferable_tokens(&mut self, session_token : & SessionToken) -> SessionTokenResult<Vec<TransitionToken>> {
+        let mut v = Vec::<TransitionToken>::new();
+        match self._session_timing.get(session_token) {
+            Some(s_time_info) => {
+                if s_time_info._detachment_allowed {
+                    if let Some(token_sets) = return_::<SessionToken,SessionTokenSets>(& self._sessions_to_their_tokens,session_token) {
+                        for token in &token_sets.session_carries {
+                            v.push(token.to_string());
+                        }
+                    }
+                }
+            }
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        };
+        //
+        Ok(v)
+    }
+
+
+    //
+    //
+    fn list_sellable_tokens(&mut self) -> Vec<TransitionToken> {
+        let mut v = Vec::<TransitionToken>::new();
+        let its = self._all_tranferable_tokens.iter().collect::<Vec<_>>();
+        for (token, t_info) in &its {
+            if t_info._sellable {
+                v.push(token.to_string());
+            }
+        }
+        v
+    }
+
+    //
+    fn list_unassigned_tokens(&mut self) -> Vec<TransitionToken> {  // _orphaned_tokens
+        let mut v = Vec::<TransitionToken>::new();
+        let its = self._orphaned_tokens.iter().collect::<Vec<_>>();
+        for token in &its {
+            v.push(token.to_string());
+        }
+        v
+    }
+
+    //
+    //
+    fn list_detached_sessions(&mut self) -> Vec<SessionToken> {  // _detached_sessions
+        let mut v = Vec::<SessionToken>::new();
+        let its = self._detached_sessions.iter().collect::<Vec<_>>();
+        for token in &its {
+            v.push(token.to_string());
+        }
+        v
+    }
+
+    //
+    //
+    fn list_pending_tokens(&mut self) -> Vec<TransitionToken> {  // _pending_tokens -- soonest first
+        let mut v = Vec::<(Millis,TransitionToken)>::new();
+        for token in &self._pending_tokens {
+            if let Some(time_info) = self._token_timing.get(token) {
+                v.push((time_info._not_before,token.to_string()));
+            }
+        }
+        v.sort();
+        v.into_iter().map(|(_, token)| token).collect()
+    }
+
+
+}
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+impl<D: DB + std::marker::Unpin> LocalSessionTokens<D> {
+
+    // sign_token
+    // with a signer, the token from the creator is given its issue time and HMAC
+
+    fn sign_token(&self, token : Token) -> Token {
+        match &self._token_signer {
+            Some(signer) => {
+                let now = self._clock.now();
+                match token {
+                    Token::SessionToken(st) => Token::SessionToken(signer.sign(&st,now)),
+                    Token::TransitionToken(tt) => Token::TransitionToken(signer.sign(&tt,now)),
+                }
+            }
+            _ => token
+        }
+    }
+
+    // move_token
+    // the token leaves the yielder's session and is carried by the receiver's
+
+    async fn move_token(&mut self, t_token : & TransitionToken, yielder_key : & Ucwid, receiver_key : & Ucwid) -> SessionTokenResult<()> {
+        //
+        if !self.token_is_transferable(t_token) {
+            return Err(SessionTokenError::NotTransferable(t_token.to_string()))
+        }
+        let yielder_sessions = match self._owner_to_sessions.get(yielder_key) {
+            Some(y_sessions) => y_sessions.clone(),
+            _ => return Err(SessionTokenError::UnknownOwner(yielder_key.to_string()))
+        };
+        let rsst = match self.receiving_session(receiver_key) {
+            Some(r_session_token) => r_session_token,
+            _ => return Err(SessionTokenError::UnknownOwner(receiver_key.to_string()))
+        };
+        if self._token_to_owner.get(&Token::TransitionToken(t_token.to_string())) != Some(yielder_key) {     // orphaned tokens too -- only their owner may give them away
+            return Err(SessionTokenError::NotOwner(t_token.to_string()))
+        }
+        //
+        let value = match self._token_to_information.get(t_token) {    // get this before it is possibly removed
+            Some(value) => value.to_string(),
+            _ => match self._db.get_key_value(t_token).await? {
+                Some(value) => value,
+                _ => return Err(SessionTokenError::UnknownToken(t_token.to_string()))
+            }
+        };
+        let time_info = self._token_timing.get(t_token).cloned();       // the token keeps its deadline, not-before instant and uses
+        if time_info.as_ref().is_some_and(|time_info| time_info.is_expired(self._clock.now())) {     // not yet reaped, but gone all the same
+            return Err(SessionTokenError::Expired(t_token.to_string()))
+        }
+        if !self._orphaned_tokens.contains(t_token) {
+            let carries = yielder_sessions.iter().any(|ysst| {     // any of the yielder's sessions may carry it
+                match self._sessions_to_their_tokens.get(ysst) {
+                    Some(sess_token_set) => sess_token_set.session_carries.contains(t_token),
+                    _ => false
+                }
+            });
+            if !carries {
+                return Err(SessionTokenError::NotOwner(t_token.to_string()))
+            }
+            let scopes = self._token_scopes.get(t_token).cloned();     // the scopes go with the token
+            self.destroy_token(t_token).await?;
+            if let Some(scopes) = scopes {
+                self._token_scopes.insert(t_token.to_string(),scopes);
+            }
+        }
+
+        self.add_transferable_token(t_token, StructOrString::TypeStr(value), receiver_key).await?;
+        if let Some(time_info) = time_info {
+            self.restore_token_timing(t_token,time_info).await?;
+        }
+        self._token_to_session.insert(t_token.to_string(),rsst.to_string());
+        match self._sessions_to_their_tokens.get_mut(&rsst) {
+            Some(r_sess_token_set) => {
+                r_sess_token_set.session_carries.insert(t_token.to_string());
+                let t = Token::TransitionToken(t_token.to_string());                            
+                self._token_to_owner.insert(t,receiver_key.to_string());
+                Ok(())
+            }
+            _ => Err(SessionTokenError::UnknownSession(rsst))
+        }
+    }
+
+    // jwt_id
+    // the opaque id a JWT names a session or token by, kept so that verify_jwt can map it back
+
+    fn jwt_id(&mut self, token : Token) -> String {
+        let id = match &token {
+            Token::SessionToken(session_token) => opaque_id(&self._jwt_id_key,"session",session_token),
+            Token::TransitionToken(t_token) => opaque_id(&self._jwt_id_key,"token",t_token),
+        };
+        self._jwt_ids.insert(id.clone(),token);
+        id
+    }
+
+    // restore_token_timing
+    // puts back the timing record a token had before it was moved, in place of the fresh one it was added with,
+    // so that moving a token neither renews its time nor lifts its not-before instant.
+    // The DB count of uses is set again, since destroying the token's entry took it away.
+
+    async fn restore_token_timing(&mut self, t_token : & TransitionToken, mut time_info : TokenTimingInfo) -> SessionTokenResult<()> {
+        time_info.attach();     // a token taken up by a session is no longer waiting out its disownment
+        self._token_deadlines.schedule(t_token.to_string(),time_info.expires_at());
+        if time_info.is_pending(self._clock.now()) {
+            self._pending_tokens.insert(t_token.to_string());
+            self._activation_deadlines.schedule(t_token.to_string(),time_info._not_before);
+        }
+        if let Some(uses) = time_info._uses_left {
+            self._db.set_use_count(t_token,uses).await?;
+        }
+        self._token_timing.insert(t_token.to_string(),time_info);
+        Ok(())
+    }
+
+    // carry_token
+    // the token is carried by the given session of its owner.
+    // A token already in the tables keeps its timing record -- being taken up by a session does not start its time over.
+
+    async fn carry_token(&mut self, t_token : & TransitionToken, value : StructOrString<serde_json::Value>, ownership_key : & Ucwid, sst : SessionToken) -> SessionTokenResult<()> {
+        if !self._sessions_to_their_tokens.contains_key(&sst) {
+            return Err(SessionTokenError::UnknownSession(sst))
+        }
+        let time_info = self._token_timing.get(t_token).cloned();
+        if time_info.as_ref().is_some_and(|time_info| time_info.is_expired(self._clock.now())) {
+            return Err(SessionTokenError::Expired(t_token.to_string()))
+        }
+        if let Some((_, policy)) = self._prefix_registry.classify(t_token) {
+            if !policy._transferable {
+                return Err(SessionTokenError::NotTransferable(t_token.to_string()))
+            }
+        }
+        //
+        let mut tt_info = TransferableTokenInfoBuilder::default()._owner(ownership_key.clone()).build()?;
+        let store_value : String;
+        let deser_val : Value;
+        match value {
+            StructOrString::TypeStr(sval) => {
+                deser_val = serde_json::from_str(sval.as_str())?;
+                store_value = sval;
+            }
+            StructOrString::TypeGen(struct_val) => {
+                deser_val = struct_val.clone();
+                let jval = struct_val.to_string();
+                store_value = jval;
+            }
+        };
+        tt_info.set_all(deser_val)?;
+        //
+        if let Some(sess_token_set) = self._sessions_to_their_tokens.get_mut(&sst) {
+            sess_token_set.session_carries.insert(t_token.to_string());
+        }
+        let tt = Token::TransitionToken(t_token.to_string());
+        self._token_to_owner.insert(tt,ownership_key.to_string());
+        self._token_to_session.insert(t_token.to_string(), sst);
+        self._all_tranferable_tokens.insert(t_token.to_string(),tt_info);
+        self.add_token(t_token,StructOrString::TypeStr(store_value)).await?;
+        match time_info {
+            Some(time_info) => self.restore_token_timing(t_token,time_info).await,
+            _ => Ok(())
+        }
+    }
+
+    // bind_token
+    // adds a token that goes when the session goes
+
+    async fn bind_token(&mut self, t_token : & TransitionToken, session_token : & SessionToken, value : &str, timeout : i32) -> SessionTokenResult<()> {
+        match self._sessions_to_their_tokens.get_mut(session_token) {
+            Some(sess_token_set) => {
+                sess_token_set.session_bounded.insert(t_token.to_string());
+            }
+            _ => return Err(SessionTokenError::UnknownSession(session_token.to_string()))
+        }
+        self._token_to_session.insert(t_token.to_string(),session_token.to_string());
+        if let Some(owner) = self._session_to_owner.get(session_token) {
+            self._token_to_owner.insert(Token::TransitionToken(t_token.to_string()),owner.to_string());
+        }
+        self.add_token(t_token,StructOrString::TypeStr(value.to_string())).await?;
+        self.set_token_timeout(t_token,timeout)
+    }
+
+    // issue_from_family
+    // a new access token for the family, and a new refresh token if asked for -- the refresh field is empty otherwise
+
+    async fn issue_from_family(&mut self, family : RefreshFamily, with_refresh : bool) -> SessionTokenResult<TokenPair> {
+        let access = token_text(self.create_token(Some(ACCESS_PREFIX.to_string())));
+        let value = serde_json::json!({ "_session" : family._session }).to_string();
+        self.bind_token(&access, &family._session, &value, family._access_timeout).await?;
+        let mut refresh = String::new();
+        if with_refresh {
+            refresh = token_text(self.create_token(Some(REFRESH_PREFIX.to_string())));
+            self.bind_token(&refresh, &family._session, &value, family._refresh_timeout).await?;
+            self._refresh_families.insert(refresh.to_string(),family);
+        }
+        Ok(TokenPair { _access : access, _refresh : refresh })
+    }
+
+    // check_signature
+    // when tokens are signed, one that does not verify is refused before the tables or the DB are consulted
+
+    fn check_signature(&self, token : & TransitionToken) -> SessionTokenResult<()> {
+        match &self._token_signer {
+            Some(signer) if !signer.verify(token) => Err(SessionTokenError::Forged(token.to_string())),
+            _ => Ok(())
+        }
+    }
+
+    // check_token_live
+    // a token in the tables that has neither expired nor is waiting for its not-before instant
+
+    fn check_token_live(&self, t_token : & TransitionToken) -> SessionTokenResult<()> {
+        let now = self._clock.now();
+        match self._token_timing.get(t_token) {
+            Some(time_info) if time_info.is_expired(now) => Err(SessionTokenError::Expired(t_token.to_string())),
+            Some(time_info) if time_info.is_pending(now) => Err(SessionTokenError::NotYetActive(t_token.to_string())),
+            Some(_) => Ok(()),
+            _ => Err(SessionTokenError::UnknownToken(t_token.to_string()))
+        }
+    }
+
+    // compact_deadlines
+    // the queues keep old entries for deadlines that have moved -- drop them once they outnumber the live ones
+
+    fn compact_deadlines(&mut self) {
+        let session_timing = &self._session_timing;
+        if self._session_deadlines.len() > 2*session_timing.len() + DEADLINE_SLACK {
+            self._session_deadlines.retain(|st, at| session_timing.get(st).is_some_and(|ti| ti.expires_at() == at));
+        }
+        let token_timing = &self._token_timing;
+        if self._token_deadlines.len() > 2*token_timing.len() + DEADLINE_SLACK {
+            self._token_deadlines.retain(|tt, at| token_timing.get(tt).is_some_and(|ti| ti.expires_at() == at));
+        }
+        let pending_tokens = &self._pending_tokens;
+        if self._activation_deadlines.len() > 2*pending_tokens.len() + DEADLINE_SLACK {
+            self._activation_deadlines.retain(|tt, at| pending_tokens.contains(tt) && token_timing.get(tt).is_some_and(|ti| ti._not_before == at));
+        }
+    }
+
+    // receiving_session
+    // the session that receives an owner's new tokens -- the newest of its attached sessions,
+    // or the newest detached one if all of them are detached
+
+    fn receiving_session(&self, ownership_key : & Ucwid) -> Option<SessionToken> {
+        let sessions = self._owner_to_sessions.get(ownership_key)?;
+        match sessions.iter().rev().find(|st| !self._detached_sessions.contains(*st)) {
+            Some(st) => Some(st.to_string()),
+            _ => sessions.last().cloned()
+        }
+    }
+
+    fn unlist_session(&mut self, ownership_key : & Ucwid, session_token : & SessionToken) {
+        if let Some(sessions) = self._owner_to_sessions.get_mut(ownership_key) {
+            sessions.retain(|st| st != session_token);
+            if sessions.is_empty() {
+                self._owner_to_sessions.remove(ownership_key);
+            }
+        }
+    }
+
+    // sessions_to_displace
+    // applies the session limit to the owner's other live sessions -- returns the sessions to end to make room.
+    // Expired sessions the reaper has yet to remove do not count against the limit.
+
+    fn sessions_to_displace(&self, session_token : & SessionToken, ownership_key : & Ucwid) -> SessionTokenResult<Vec<SessionToken>> {
+        let now = self._clock.now();
+        let others : Vec<SessionToken> = self.sessions_for_owner(ownership_key).into_iter().filter(|st| {
+            st != session_token && !self._session_timing.get(st).is_some_and(|time_info| time_info.is_expired(now))
+        }).collect();
+        let keep = match self._session_limit {
+            SessionLimit::Unlimited => return Ok(vec![]),
+            SessionLimit::Reject(max) => {
+                if others.len() >= max {
+                    return Err(SessionTokenError::TooManySessions(ownership_key.to_string()))
+                }
+                return Ok(vec![])
+            }
+            SessionLimit::EvictOldest(max) => max.max(1) - 1,
+            SessionLimit::Single => 0,
+        };
+        let evict_count = others.len().saturating_sub(keep);
+        Ok(others.into_iter().take(evict_count).collect())      // oldest first
+    }
+
+    // remove_session
+    // takes a session out of all tables given the session token itself -- used by destroy_session and by expiry
+
+    async fn remove_session(&mut self, session_token : & SessionToken) -> SessionTokenResult<()> {
+        let now = self._clock.now();
+        //
+        self._detached_sessions.remove(session_token);
+        self._session_challenges.remove(session_token);
+        self._proven_sessions.remove(session_token);
+        if let Some(owner) = self._session_to_owner.remove(session_token) {
+            self.unlist_session(&owner,session_token);
+        }
+        self._session_checking_tokens.remove(session_token);
+        if let Some(time_info) = self._session_timing.get_mut(session_token) {
+            if time_info._shared {
+                self._db.del_key_value(&session_token.to_string()).await?;
+            }
+        }
+        //
+        self._session_timing.remove(session_token);
+        let st = Token::SessionToken(session_token.to_string());
+        self._token_to_owner.remove(&st);
+        self._jwt_ids.remove(&opaque_id(&self._jwt_id_key,"session",session_token));
+        //
+
+        if let Some(token_sets) = return_::<SessionToken,SessionTokenSets>(& self._sessions_to_their_tokens,session_token) {
+            for token in &token_sets.session_carries {
+                self._orphaned_tokens.insert(token.to_string());            // orphaned
+                if let Some(time_info) = self._token_timing.get_mut(token) {
+                    time_info.detach(now);      // the disownment timeout starts now
+                    self._token_deadlines.schedule(token.to_string(),time_info.expires_at());
+                }
+            }
+            for token in &token_sets.session_bounded {
+                self.destroy_token(token).await?;
+            }
+        }
+
+        self._sessions_to_their_tokens.remove(session_token);
+        self._spent_refresh_tokens.retain(|_, st| st != session_token);
+        self._db.del_session_key_value(&session_token.to_string()).await?;
+        //
+        let rotated_away : Vec<SessionToken> = self._rotated_sessions.iter().filter(|(_, (target, _))| target == session_token).map(|(old, _)| old.to_string()).collect();
+        for old_session in rotated_away {
+            self._rotated_sessions.remove(&old_session);
+            self._db.del_session_key_value(&old_session).await?;
+            self._db.del_key_value(&old_session).await?;
+        }
+        Ok(())
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::memory_db::InMemoryDB;
+    use crate::timing::MINUTES;
+
+    #[async_std::test]
+    async fn time_left_follows_the_clock() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &"owner-1".to_string(), None, None).await.unwrap();
+        //
+        clock.advance((10*MINUTES) as u64);
+        assert_eq!(tables.get_session_time_left(&session).unwrap(), 50*MINUTES);
+    }
+
+    #[async_std::test]
+    async fn expired_session_takes_its_bounded_tokens() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let session = "user+abc".to_string();
+        let owner = "owner-1".to_string();
+        let t_token = "media+xyz".to_string();
+        tables.add_session(&session, &owner, Some(t_token.clone()), None).await.unwrap();
+        //
+        clock.advance((61*MINUTES) as u64);
+        assert_eq!(tables.active_session(&session, &owner).await, Err(SessionTokenError::Expired(session.clone())));
+        tables.decrement_timers().await;
+        //
+        assert_eq!(tables.get_session_time_left(&session), Err(SessionTokenError::UnknownSession(session.clone())));
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Err(SessionTokenError::UnknownToken(t_token.clone())));
+    }
+
+    #[async_std::test]
+    async fn failures_say_why() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let t_token = "ticket+abc".to_string();
+        tables.add_session(&"user+abc".to_string(), &owner, None, None).await.unwrap();
+        let bad_json = tables.add_transferable_token(&t_token, StructOrString::TypeStr("{not json".to_string()), &owner).await;
+        assert!(matches!(bad_json, Err(SessionTokenError::Serialization(_))));
+        //
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &owner).await.unwrap();
+        let stranger = "owner-2".to_string();       // a yielder with no session
+        assert_eq!(tables.transfer_token(&t_token, &stranger, &owner).await, Err(SessionTokenError::UnknownOwner(stranger.clone())));
+        assert_eq!(tables.transfer_token(&"ticket+xyz".to_string(), &owner, &stranger).await, Err(SessionTokenError::NotTransferable("ticket+xyz".to_string())));
+        assert_eq!(tables.from_token(t_token.clone()), Ok(owner.clone()));
+    }
+
+    #[async_std::test]
+    async fn detached_session_runs_on_its_detachment_allotment() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &"owner-1".to_string(), None, None).await.unwrap();
+        //
+        clock.advance((10*MINUTES) as u64);
+        tables.detach_session(session.clone()).await.unwrap();
+        clock.advance((51*MINUTES) as u64);     // past the session's own hour
+        assert_eq!(tables.decrement_timers().await, 0);
+        assert_eq!(tables.get_session_time_left(&session).unwrap(), 9*MINUTES);
+        //
+        clock.advance((10*MINUTES) as u64);
+        assert_eq!(tables.decrement_timers().await, 1);
+        assert!(tables.list_detached_sessions().is_empty());
+    }
+
+    #[async_std::test]
+    async fn signed_tokens_are_checked_before_the_db() {
+        let db = InMemoryDB::new();
+        let mut tables = LocalSessionTokens::new(db.clone(), None);
+        tables.set_token_signer(Some(TokenSigner::new(1, b"a secret")));
+        let t_token = match tables.create_token(Some("media+".to_string())) {
+            Token::TransitionToken(tt) => tt,
+            _ => panic!("expected a transition token")
+        };
+        tables.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Ok("{}".to_string()));
+        //
+        let forged = "media+0123456789abcdef".to_string();
+        db.set_key_value(&forged, "{}").await.unwrap();
+        assert_eq!(tables.transition_token_is_active(&forged).await, Err(SessionTokenError::Forged(forged.clone())));
+    }
+
+    #[async_std::test]
+    async fn token_creators_may_carry_state() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use crate::tokens::AsyncTokenGenerator;
+        //
+        struct CountingService {
+            _next : AtomicU64,
+        }
+        #[async_trait]
+        impl AsyncTokenGenerator for CountingService {
+            async fn generate(&self, prefix : Option<&str>) -> Token {
+                let n = self._next.fetch_add(1, Ordering::SeqCst);
+                Token::TransitionToken(format!("{}{}", prefix.unwrap_or(""), n))
+            }
+        }
+        //
+        let node = "node-7".to_string();
+        let creator = move |prefix : Option<&str>| Token::TransitionToken(format!("{}{}", prefix.unwrap_or(""), node));
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), Some(Box::new(creator)));
+        assert_eq!(tables.create_token(Some("media+".to_string())), Token::TransitionToken("media+node-7".to_string()));
+        assert_eq!(tables.create_token_async(None).await, Token::TransitionToken("node-7".to_string()));
+        //
+        tables.set_async_token_creator(Some(Box::new(CountingService { _next : AtomicU64::new(1) })));
+        assert_eq!(tables.create_token_async(Some("order+".to_string())).await, Token::TransitionToken("order+1".to_string()));
+        assert_eq!(tables.create_token_async(Some("order+".to_string())).await, Token::TransitionToken("order+2".to_string()));
+    }
+
+    #[async_std::test]
+    async fn registered_prefixes_set_kind_and_policy() {
+        use crate::prefixes::{TokenKind, TokenPolicyBuilder};
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.register_token_prefix("admin+", TokenPolicyBuilder::default()._kind(TokenKind::Session).build().unwrap());
+        tables.register_token_prefix("media+", TokenPolicyBuilder::default()._timeout(Some(5*MINUTES)).build().unwrap());
+        tables.register_token_prefix("order+", TokenPolicyBuilder::default()._sellable(true).build().unwrap());
+        tables.register_token_prefix("receipt+", TokenPolicyBuilder::default()._transferable(false).build().unwrap());
+        assert!(matches!(tables.create_token(Some("admin+".to_string())), Token::SessionToken(_)));
+        //
+        let media = "media+abc".to_string();
+        tables.add_token(&media, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        assert_eq!(tables.get_token_timeout(&media), Ok(5*MINUTES));
+        //
+        let session = "user+abc".to_string();
+        let owner = "owner-1".to_string();
+        tables.add_session(&session, &owner, None, None).await.unwrap();
+        let order = "order+abc".to_string();
+        tables.add_transferable_token(&order, StructOrString::TypeStr("{}".to_string()), &owner).await.unwrap();
+        assert_eq!(tables.list_sellable_tokens(), vec![order.clone()]);
+        let receipt = "receipt+abc".to_string();
+        assert_eq!(tables.add_transferable_token(&receipt, StructOrString::TypeStr("{}".to_string()), &owner).await, Err(SessionTokenError::NotTransferable(receipt.clone())));
+    }
+
+    #[async_std::test]
+    async fn owners_hold_several_sessions() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let phone = "user+phone".to_string();
+        let laptop = "user+laptop".to_string();
+        tables.add_session(&phone, &owner, None, None).await.unwrap();
+        tables.add_session(&laptop, &owner, None, None).await.unwrap();
+        assert_eq!(tables.sessions_for_owner(&owner), vec![phone.clone(), laptop.clone()]);
+        //
+        let t_token = "media+xyz".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &owner).await.unwrap();
+        tables.allow_session_detach(laptop.clone()).unwrap();
+        assert_eq!(tables.list_tranferable_tokens(&laptop), Ok(vec![t_token.clone()]));     // the newest session receives it
+        //
+        assert_eq!(tables.destroy_all_sessions_for_owner(&owner).await, Ok(2));
+        assert!(tables.sessions_for_owner(&owner).is_empty());
+        assert_eq!(tables.list_unassigned_tokens(), vec![t_token.clone()]);
+    }
+
+    #[async_std::test]
+    async fn session_limit_rejects_or_evicts() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let sessions : Vec<SessionToken> = (1..=4).map(|n| format!("user+{}", n)).collect();
+        //
+        tables.set_session_limit(SessionLimit::Reject(2));
+        tables.add_session(&sessions[0], &owner, None, None).await.unwrap();
+        tables.add_session(&sessions[1], &owner, None, None).await.unwrap();
+        assert_eq!(tables.add_session(&sessions[2], &owner, None, None).await, Err(SessionTokenError::TooManySessions(owner.clone())));
+        assert_eq!(tables.sessions_for_owner(&owner), vec![sessions[0].clone(), sessions[1].clone()]);
+        //
+        tables.set_session_limit(SessionLimit::EvictOldest(2));
+        let added = tables.add_session(&sessions[2], &owner, None, None).await.unwrap();
+        assert_eq!(added._displaced, vec![sessions[0].clone()]);
+        assert_eq!(tables.get_session_timeout(&sessions[0]), Err(SessionTokenError::UnknownSession(sessions[0].clone())));
+        //
+        tables.set_session_limit(SessionLimit::Single);
+        let added = tables.add_session(&sessions[3], &owner, None, None).await.unwrap();
+        assert_eq!(added._displaced, vec![sessions[1].clone(), sessions[2].clone()]);
+        assert_eq!(tables.sessions_for_owner(&owner), vec![sessions[3].clone()]);
+    }
+
+    #[async_std::test]
+    async fn expired_sessions_do_not_count_against_the_limit() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        tables.set_session_limit(SessionLimit::Reject(1));
+        let owner = "owner-1".to_string();
+        tables.add_session(&"user+abc".to_string(), &owner, None, None).await.unwrap();
+        //
+        clock.advance((61*MINUTES) as u64);     // past the hour, before the reaper has run
+        assert!(tables.add_session(&"user+xyz".to_string(), &owner, None, None).await.is_ok());
+    }
+
+    #[async_std::test]
+    async fn touched_sessions_slide_until_the_absolute_limit() {
+        let clock = MockClock::default();
+        let db = InMemoryDB::new();
+        let mut tables = LocalSessionTokens::new(db.clone(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &"owner-1".to_string(), None, Some(true)).await.unwrap();
+        tables.set_session_idle_timeout(&session, Some(20*MINUTES)).await.unwrap();
+        //
+        for _ in 0..2 {
+            clock.advance((15*MINUTES) as u64);
+            tables.touch_session(&session).await.unwrap();
+        }
+        assert_eq!(tables.get_session_time_left(&session).unwrap(), 20*MINUTES);
+        let stored : Value = serde_json::from_str(&db.get_key_value(&session).await.unwrap().unwrap()).unwrap();
+        assert_eq!(stored["_last_touched"], serde_json::json!(clock.now()));
+        //
+        clock.advance((15*MINUTES) as u64);
+        tables.touch_session(&session).await.unwrap();
+        assert_eq!(tables.get_session_time_left(&session).unwrap(), 15*MINUTES);     // the hour is nearly up
+        clock.advance((15*MINUTES) as u64);
+        assert_eq!(tables.touch_session(&session).await, Err(SessionTokenError::Expired(session.clone())));
+    }
+
+    #[async_std::test]
+    async fn rotated_sessions_keep_their_tokens() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let t_token = "media+xyz".to_string();
+        tables.add_session(&session, &owner, Some(t_token.clone()), None).await.unwrap();
+        //
+        let rotated = tables.rotate_session(&session, None).await.unwrap();
+        assert_eq!(tables.active_session(&session, &owner).await, Err(SessionTokenError::UnknownSession(session.clone())));
+        assert!(tables.active_session(&rotated, &owner).await.unwrap());
+        assert_eq!(tables.sessions_for_owner(&owner), vec![rotated.clone()]);
+        tables.destroy_session(&t_token).await.unwrap();        // the bounded token now leads to the new session
+        assert!(tables.sessions_for_owner(&owner).is_empty());
+    }
+
+    #[async_std::test]
+    async fn rotated_session_has_a_grace_period() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &owner, None, Some(true)).await.unwrap();
+        //
+        let rotated = tables.rotate_session(&session, Some(30*1000)).await.unwrap();
+        assert!(tables.active_session(&session, &owner).await.unwrap());
+        assert!(tables._db.get_key_value(&session).await.unwrap().is_some());      // other processes sharing the DB still see it
+        assert!(tables._db.get_key_value(&rotated).await.unwrap().is_some());
+        clock.advance(30*1000);
+        assert_eq!(tables.active_session(&session, &owner).await, Err(SessionTokenError::Expired(session.clone())));
+        tables.decrement_timers().await;
+        assert_eq!(tables.active_session(&session, &owner).await, Err(SessionTokenError::UnknownSession(session.clone())));
+        assert!(tables._db.get_key_value(&session).await.unwrap().is_none());
+        assert!(tables.active_session(&rotated, &owner).await.unwrap());
+    }
+
+    #[async_std::test]
+    async fn replayed_refresh_token_ends_the_session() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &owner, None, None).await.unwrap();
+        //
+        let first = tables.issue_token_pair(&session, 5*MINUTES, 60*MINUTES).await.unwrap();
+        assert_eq!(tables.get_token_timeout(&first._access), Ok(5*MINUTES));
+        let same = tables.redeem_refresh_token(&first._refresh, false).await.unwrap();
+        assert_eq!(same._refresh, first._refresh);
+        let second = tables.redeem_refresh_token(&first._refresh, true).await.unwrap();
+        assert!(tables.transition_token_is_active(&second._access).await.is_ok());
+        //
+        assert_eq!(tables.redeem_refresh_token(&first._refresh, true).await, Err(SessionTokenError::Replayed(first._refresh.clone())));
+        assert!(tables.sessions_for_owner(&owner).is_empty());
+        assert_eq!(tables.redeem_refresh_token(&second._refresh, true).await, Err(SessionTokenError::UnknownToken(second._refresh.clone())));
+    }
+
+    #[async_std::test]
+    async fn refresh_tokens_follow_a_rotated_session() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &owner, None, None).await.unwrap();
+        let first = tables.issue_token_pair(&session, 5*MINUTES, 60*MINUTES).await.unwrap();
+        let second = tables.redeem_refresh_token(&first._refresh, true).await.unwrap();
+        //
+        let rotated = tables.rotate_session(&session, None).await.unwrap();
+        let third = tables.redeem_refresh_token(&second._refresh, true).await.unwrap();
+        assert!(tables.transition_token_is_active(&third._access).await.is_ok());
+        //
+        assert_eq!(tables.redeem_refresh_token(&first._refresh, true).await, Err(SessionTokenError::Replayed(first._refresh.clone())));
+        assert!(tables.sessions_for_owner(&owner).is_empty());
+        assert_eq!(tables.active_session(&rotated, &owner).await, Err(SessionTokenError::UnknownSession(rotated.clone())));
+    }
+
+    #[async_std::test]
+    async fn last_use_is_taken_once_across_processes() {
+        let db = InMemoryDB::new();
+        let mut here = LocalSessionTokens::new(db.clone(), None);
+        let mut there = LocalSessionTokens::new(db.clone(), None);
+        let t_token = "reset+abc".to_string();
+        here.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        here.set_token_uses(&t_token, 2).await.unwrap();
+        there.transition_token_is_active(&t_token).await.unwrap();
+        //
+        assert_eq!(there.consume_token(&t_token).await, Ok("{}".to_string()));
+        assert_eq!(here.consume_token(&t_token).await, Ok("{}".to_string()));
+        assert_eq!(here.transition_token_is_active(&t_token).await, Err(SessionTokenError::UnknownToken(t_token.clone())));
+        assert_eq!(there.consume_token(&t_token).await, Err(SessionTokenError::UsedUp(t_token.clone())));
+    }
+
+    #[async_std::test]
+    async fn transferred_tokens_keep_their_uses() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let yielder = "owner-1".to_string();
+        let receiver = "owner-2".to_string();
+        tables.add_session(&"user+abc".to_string(), &yielder, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &receiver, None, None).await.unwrap();
+        let t_token = "ticket+abc".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &yielder).await.unwrap();
+        tables.set_token_uses(&t_token, 2).await.unwrap();
+        tables.consume_token(&t_token).await.unwrap();
+        //
+        tables.transfer_token(&t_token, &yielder, &receiver).await.unwrap();
+        assert_eq!(tables.consume_token(&t_token).await, Ok("{}".to_string()));
+        assert_eq!(tables.consume_token(&t_token).await, Err(SessionTokenError::UnknownToken(t_token.clone())));
+    }
+
+    #[async_std::test]
+    async fn tokens_wait_for_their_not_before_instant() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let t_token = "ticket+abc".to_string();
+        let later_timeout = "ticket+xyz".to_string();
+        tables.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        tables.add_token(&later_timeout, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        tables.set_token_timeout(&t_token, 10*MINUTES).unwrap();
+        tables.set_token_not_before(&t_token, clock.now() + (30*MINUTES) as u64).unwrap();
+        tables.set_token_not_before(&later_timeout, clock.now() + (30*MINUTES) as u64).unwrap();
+        tables.set_token_timeout(&later_timeout, 10*MINUTES).unwrap();     // the order of the calls does not matter
+        //
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Err(SessionTokenError::NotYetActive(t_token.clone())));
+        assert_eq!(tables.list_pending_tokens().len(), 2);
+        clock.advance((35*MINUTES) as u64);
+        tables.decrement_timers().await;
+        assert!(tables.list_pending_tokens().is_empty());
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Ok("{}".to_string()));
+        assert_eq!(tables.get_token_time_left(&t_token), Ok(5*MINUTES));
+        assert_eq!(tables.get_token_time_left(&later_timeout), Ok(5*MINUTES));
+    }
+
+    #[async_std::test]
+    async fn a_past_not_before_instant_changes_nothing() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let t_token = "ticket+abc".to_string();
+        tables.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        tables.set_token_timeout(&t_token, 10*MINUTES).unwrap();
+        clock.advance((4*MINUTES) as u64);
+        //
+        tables.set_token_not_before(&t_token, 0).unwrap();
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Ok("{}".to_string()));
+        assert_eq!(tables.get_token_time_left(&t_token), Ok(6*MINUTES));
+        tables.set_token_not_before(&t_token, clock.now() - MINUTES as u64).unwrap();
+        assert!(tables.list_pending_tokens().is_empty());
+        assert_eq!(tables.get_token_time_left(&t_token), Ok(6*MINUTES));
+    }
+
+    #[async_std::test]
+    async fn transferred_tokens_keep_their_timing() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let yielder = "owner-1".to_string();
+        let receiver = "owner-2".to_string();
+        tables.add_session(&"user+abc".to_string(), &yielder, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &receiver, None, None).await.unwrap();
+        let t_token = "ticket+abc".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &yielder).await.unwrap();
+        tables.set_token_timeout(&t_token, 10*MINUTES).unwrap();
+        tables.set_token_not_before(&t_token, clock.now() + (30*MINUTES) as u64).unwrap();
+        //
+        clock.advance((5*MINUTES) as u64);
+        tables.transfer_token(&t_token, &yielder, &receiver).await.unwrap();
+        assert_eq!(tables.from_token(t_token.clone()), Ok(receiver.clone()));
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Err(SessionTokenError::NotYetActive(t_token.clone())));
+        assert_eq!(tables.list_pending_tokens(), vec![t_token.clone()]);
+        assert_eq!(tables.get_token_time_left(&t_token), Ok(35*MINUTES));
+        clock.advance((25*MINUTES) as u64);
+        tables.decrement_timers().await;
+        assert_eq!(tables.transition_token_is_active(&t_token).await, Ok("{}".to_string()));
+    }
+
+    #[async_std::test]
+    async fn expired_tokens_are_not_moved_or_renewed() {
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let yielder = "owner-1".to_string();
+        let receiver = "owner-2".to_string();
+        tables.add_session(&"user+abc".to_string(), &yielder, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &receiver, None, None).await.unwrap();
+        let t_token = "ticket+abc".to_string();
+        let loose = "ticket+xyz".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &yielder).await.unwrap();
+        tables.set_token_timeout(&t_token, 10*MINUTES).unwrap();
+        tables.add_token(&loose, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        tables.set_token_timeout(&loose, 10*MINUTES).unwrap();
+        //
+        clock.advance((5*MINUTES) as u64);
+        tables.acquire_token(&loose, &"user+xyz".to_string(), &receiver).await.unwrap();
+        assert_eq!(tables.get_token_time_left(&loose), Ok(5*MINUTES));
+        clock.advance((5*MINUTES) as u64);
+        assert_eq!(tables.transfer_token(&t_token, &yielder, &receiver).await, Err(SessionTokenError::Expired(t_token.clone())));
+        assert_eq!(tables.from_token(t_token.clone()), Ok(yielder.clone()));
+        assert_eq!(tables.transfer_token(&loose, &receiver, &yielder).await, Err(SessionTokenError::Expired(loose.clone())));
+    }
+
+    #[async_std::test]
+    async fn scopes_authorize_and_only_narrow() {
+        use crate::scopes::scope_set;
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let t_token = "media+abc".to_string();
+        tables.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        assert_eq!(tables.check_token_scope(&t_token, "media:read"), Ok(false));
+        tables.set_token_scopes(&t_token, scope_set(&["media:read", "media:write"])).unwrap();
+        assert_eq!(tables.check_token_scope(&t_token, "media:write"), Ok(true));
+        //
+        tables.narrow_token_scopes(&t_token, &scope_set(&["media:read"])).unwrap();
+        assert_eq!(tables.check_token_scope(&t_token, "media:write"), Ok(false));
+        assert_eq!(tables.narrow_token_scopes(&t_token, &scope_set(&["media:write"])), Err(SessionTokenError::OutOfScope(t_token.clone())));
+        assert_eq!(tables.token_scopes(&t_token), Ok(scope_set(&["media:read"])));
+    }
+
+    #[async_std::test]
+    async fn derived_tokens_are_narrower_and_go_with_their_parent() {
+        use crate::derived::TokenRestrictionsBuilder;
+        use crate::scopes::scope_set;
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let parent = "media+abc".to_string();
+        tables.add_session(&session, &owner, Some(parent.clone()), None).await.unwrap();
+        tables.set_token_timeout(&parent, 10*MINUTES).unwrap();
+        tables.set_token_scopes(&parent, scope_set(&["media:read", "media:write"])).unwrap();
+        //
+        let restrictions = TokenRestrictionsBuilder::default()._scopes(Some(scope_set(&["media:read"])))._timeout(Some(60*MINUTES)).build().unwrap();
+        let child = tables.derive_token(&parent, restrictions).await.unwrap();
+        assert_eq!(tables.check_token_scope(&child, "media:write"), Ok(false));
+        assert_eq!(tables.get_token_timeout(&child), Ok(10*MINUTES));
+        assert_eq!(tables.from_token(child.clone()), Ok(owner.clone()));
+        let grandchild = tables.derive_token(&child, TokenRestrictions::default()).await.unwrap();
+        let wider = TokenRestrictionsBuilder::default()._scopes(Some(scope_set(&["media:write"]))).build().unwrap();
+        assert_eq!(tables.derive_token(&child, wider).await, Err(SessionTokenError::OutOfScope(child.clone())));
+        tables.set_token_uses(&grandchild, 1).await.unwrap();
+        assert_eq!(tables.derive_token(&grandchild, TokenRestrictions::default()).await, Err(SessionTokenError::LimitedUse(grandchild.clone())));
+        //
+        tables.destroy_token(&parent).await.unwrap();
+        assert_eq!(tables.transition_token_is_active(&child).await, Err(SessionTokenError::UnknownToken(child.clone())));
+        assert_eq!(tables.transition_token_is_active(&grandchild).await, Err(SessionTokenError::UnknownToken(grandchild.clone())));
+    }
+
+    #[async_std::test]
+    async fn acquired_tokens_stay_limited() {
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let t_token = "ticket+abc".to_string();
+        tables.add_session(&session, &owner, None, None).await.unwrap();
+        tables.add_token(&t_token, StructOrString::TypeStr("{}".to_string())).await.unwrap();
+        tables.set_token_uses(&t_token, 2).await.unwrap();
+        //
+        tables.acquire_token(&t_token, &session, &owner).await.unwrap();
+        assert_eq!(tables.derive_token(&t_token, TokenRestrictions::default()).await, Err(SessionTokenError::LimitedUse(t_token.clone())));
+        tables.consume_token(&t_token).await.unwrap();
+        tables.consume_token(&t_token).await.unwrap();
+        assert_eq!(tables.consume_token(&t_token).await, Err(SessionTokenError::UnknownToken(t_token.clone())));
+    }
+
+    #[async_std::test]
+    async fn caveat_tokens_verify_offline_and_against_the_tables() {
+        use crate::caveats::{Caveat, CaveatContextBuilder, CaveatToken};
+        use crate::scopes::scope_set;
+        //
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let t_token = "media+abc".to_string();
+        tables.add_session(&session, &owner, Some(t_token.clone()), None).await.unwrap();
+        tables.set_token_timeout(&t_token, 10*MINUTES).unwrap();
+        tables.set_token_scopes(&t_token, scope_set(&["media:read", "media:write"])).unwrap();
+        assert!(matches!(tables.mint_caveat_token(&t_token), Err(SessionTokenError::NoRootKey)));
+        tables.set_caveat_root_key(Some(b"root key".to_vec()));
+        //
+        let minted = tables.mint_caveat_token(&t_token).unwrap();
+        let id = minted._id.clone();
+        let read_only = minted.restrict(Caveat::Scopes(scope_set(&["media:read"]))).unwrap().encode().unwrap();
+        assert_eq!(tables.verify_caveat_token(&read_only, Some("media:read")), Ok(t_token.clone()));
+        assert_eq!(tables.verify_caveat_token(&read_only, Some("media:write")), Err(SessionTokenError::CaveatUnmet(id.clone())));
+        //
+        let offline = CaveatContextBuilder::default()._now(clock.now())._scope(Some("media:read".to_string()))._owner(Some(owner.clone())).build().unwrap();
+        assert_eq!(CaveatToken::decode(&read_only).unwrap().verify(b"root key", &offline), Err(SessionTokenError::CaveatUnmet(id.clone())));
+        let offline = CaveatContextBuilder::default()._now(clock.now())._scope(Some("media:read".to_string()))._owner(Some(owner.clone()))._session(Some(session.clone())).build().unwrap();
+        assert!(CaveatToken::decode(&read_only).unwrap().verify(b"root key", &offline).is_ok());
+        //
+        tables.set_token_not_before(&t_token, clock.now() + 60_000).unwrap();
+        assert_eq!(tables.verify_caveat_token(&read_only, Some("media:read")), Err(SessionTokenError::NotYetActive(t_token.clone())));
+        tables.destroy_token(&t_token).await.unwrap();
+        assert_eq!(tables.verify_caveat_token(&read_only, Some("media:read")), Err(SessionTokenError::UnknownToken(id.clone())));
+    }
+
+    #[async_std::test]
+    async fn caveat_tokens_give_away_no_credentials() {
+        use crate::caveats::{Caveat, CaveatToken};
+        use crate::scopes::scope_set;
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let t_token = "media+abc".to_string();
+        tables.add_session(&session, &owner, Some(t_token.clone()), None).await.unwrap();
+        tables.set_token_scopes(&t_token, scope_set(&["media:read", "media:write"])).unwrap();
+        tables.set_caveat_root_key(Some(b"root key".to_vec()));
+        //
+        let read_only = tables.mint_caveat_token(&t_token).unwrap().restrict(Caveat::Scopes(scope_set(&["media:read"]))).unwrap().encode().unwrap();
+        let decoded = CaveatToken::decode(&read_only).unwrap();
+        let readable = serde_json::to_string(&decoded).unwrap();
+        for credential in [&t_token, &session, &owner] {
+            assert!(!readable.contains(credential.as_str()));
+        }
+        assert_eq!(tables.check_token_scope(&decoded._id, "media:write"), Err(SessionTokenError::UnknownToken(decoded._id.clone())));
+        assert!(!tables.active_session(&decoded._id, &owner).await.unwrap_or(false));
+        assert_eq!(tables.verify_caveat_token(&read_only, Some("media:read")), Ok(t_token.clone()));
+    }
+
+    #[async_std::test]
+    async fn jwts_are_revoked_with_their_session_or_token() {
+        use crate::scopes::scope_set;
+        //
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let owner = "owner-1".to_string();
+        let session = "user+abc".to_string();
+        let t_token = "media+abc".to_string();
+        tables.add_session(&session, &owner, Some(t_token.clone()), None).await.unwrap();
+        tables.set_token_scopes(&t_token, scope_set(&["media:read"])).unwrap();
+        assert_eq!(tables.session_jwt(&session), Err(SessionTokenError::NoSigningKey));
+        tables.set_jwt_key(Some(JwtKey::Hs256(b"a secret".to_vec())));
+        //
+        let session_jwt = tables.session_jwt(&session).unwrap();
+        let token_jwt = tables.token_jwt(&t_token).unwrap();
+        let claims = tables.verify_jwt(&token_jwt).unwrap();
+        let session_claims = tables.verify_jwt(&session_jwt).unwrap();
+        assert_eq!(claims._owner, Some(owner.clone()));
+        assert_eq!(claims._session, session_claims._session);
+        assert_eq!(claims.scopes(), scope_set(&["media:read"]));
+        assert_eq!(session_claims._owner, Some(owner.clone()));
+        for jwt in [&session_jwt, &token_jwt] {
+            let payload = data_encoding::BASE64URL_NOPAD.decode(jwt.split('.').nth(1).unwrap().as_bytes()).unwrap();
+            let payload = String::from_utf8(payload).unwrap();
+            assert!(!payload.contains(session.as_str()) && !payload.contains(t_token.as_str()));
+        }
+        //
+        let (jti, sid) = (claims._token.clone().unwrap(), session_claims._session.clone().unwrap());
+        tables.set_token_not_before(&t_token, clock.now() + 60_000).unwrap();
+        assert_eq!(tables.verify_jwt(&token_jwt), Err(SessionTokenError::NotYetActive(t_token.clone())));
+        tables.destroy_token(&t_token).await.unwrap();
+        assert_eq!(tables.verify_jwt(&token_jwt), Err(SessionTokenError::UnknownToken(jti)));
+        tables.destroy_all_sessions_for_owner(&owner).await.unwrap();
+        assert_eq!(tables.verify_jwt(&session_jwt), Err(SessionTokenError::UnknownSession(sid)));
+    }
+
+    #[async_std::test]
+    async fn proven_sessions_answer_a_signed_challenge() {
+        use ed25519_dalek::{Signer, SigningKey};
+        use crate::proofs::{challenge_message, UcwidKey};
+        //
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.require_proof_of_possession(true);
+        let signer = SigningKey::from_bytes(&[7u8; 32]);
+        let owner = UcwidKey::Ed25519(signer.verifying_key()).to_ucwid();
+        let session = "user+abc".to_string();
+        tables.add_session(&session, &owner, None, None).await.unwrap();
+        assert_eq!(tables.active_session(&session, &owner).await, Err(SessionTokenError::NotProven(session.clone())));
+        //
+        let nonce = tables.issue_session_challenge(&session).unwrap();
+        let wrong = signer.sign(&challenge_message(&"user+xyz".to_string(), &nonce)).to_bytes();
+        assert_eq!(tables.answer_session_challenge(&session, &wrong), Err(SessionTokenError::Forged(session.clone())));
+        assert_eq!(tables.answer_session_challenge(&session, &wrong), Err(SessionTokenError::NotProven(session.clone())));
+        //
+        let nonce = tables.issue_session_challenge(&session).unwrap();
+        let signature = signer.sign(&challenge_message(&session, &nonce)).to_bytes();
+        tables.answer_session_challenge(&session, &signature).unwrap();
+        assert_eq!(tables.active_session(&session, &owner).await, Ok(true));
+    }
+
+    #[async_std::test]
+    async fn signed_transfers_are_checked_and_kept() {
+        use ed25519_dalek::{Signer, SigningKey};
+        use crate::proofs::UcwidKey;
+        use crate::transfer::{TransferAuthorization, TransferSigning};
+        //
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        let yielder_signer = SigningKey::from_bytes(&[7u8; 32]);
+        let receiver_signer = SigningKey::from_bytes(&[9u8; 32]);
+        let yielder = UcwidKey::Ed25519(yielder_signer.verifying_key()).to_ucwid();
+        let receiver = UcwidKey::Ed25519(receiver_signer.verifying_key()).to_ucwid();
+        tables.add_session(&"user+abc".to_string(), &yielder, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &receiver, None, None).await.unwrap();
+        let t_token = "order+abc".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &yielder).await.unwrap();
+        tables.set_transfer_signing(TransferSigning::YielderAndReceiver);
+        assert_eq!(tables.transfer_token(&t_token, &yielder, &receiver).await, Err(SessionTokenError::Unauthorized(t_token.clone())));
+        //
+        let mut authorization = TransferAuthorization::new(&t_token, &yielder, &receiver, clock.now());
+        authorization._yielder_signature = receiver_signer.sign(&authorization.signing_message()).to_bytes().to_vec();
+        assert_eq!(tables.transfer_token_signed(authorization.clone()).await, Err(SessionTokenError::Forged(t_token.clone())));
+        authorization._yielder_signature = yielder_signer.sign(&authorization.signing_message()).to_bytes().to_vec();
+        assert_eq!(tables.transfer_token_signed(authorization.clone()).await, Err(SessionTokenError::Unauthorized(t_token.clone())));
+        assert_eq!(tables.from_token(t_token.clone()), Ok(yielder.clone()));
+        //
+        authorization._receiver_signature = Some(receiver_signer.sign(&authorization.acceptance_message()).to_bytes().to_vec());
+        tables.transfer_token_signed(authorization.clone()).await.unwrap();
+        assert_eq!(tables.from_token(t_token.clone()), Ok(receiver.clone()));
+        assert_eq!(tables.transfer_proofs(&t_token), vec![authorization.clone()]);
+        assert_eq!(tables.transfer_token_signed(authorization).await, Err(SessionTokenError::Replayed(t_token.clone())));
+    }
+
+    #[async_std::test]
+    async fn orphaned_tokens_are_given_away_only_by_their_owner() {
+        use ed25519_dalek::{Signer, SigningKey};
+        use crate::proofs::UcwidKey;
+        use crate::transfer::{TransferAuthorization, TransferSigning};
+        //
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        tables.set_transfer_signing(TransferSigning::Yielder);
+        let owner = "owner-1".to_string();
+        let attacker_signer = SigningKey::from_bytes(&[7u8; 32]);
+        let attacker = UcwidKey::Ed25519(attacker_signer.verifying_key()).to_ucwid();
+        tables.add_session(&"user+abc".to_string(), &owner, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &attacker, None, None).await.unwrap();
+        let t_token = "order+abc".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &owner).await.unwrap();
+        tables.destroy_all_sessions_for_owner(&owner).await.unwrap();
+        assert_eq!(tables.list_unassigned_tokens(), vec![t_token.clone()]);
+        //
+        let mut authorization = TransferAuthorization::new(&t_token, &attacker, &attacker, clock.now());
+        authorization._yielder_signature = attacker_signer.sign(&authorization.signing_message()).to_bytes().to_vec();
+        assert_eq!(tables.transfer_token_signed(authorization).await, Err(SessionTokenError::NotOwner(t_token.clone())));
+        assert_eq!(tables.from_token(t_token.clone()), Ok(owner.clone()));
+        assert_eq!(tables.list_unassigned_tokens(), vec![t_token.clone()]);
+    }
+
+    #[async_std::test]
+    async fn transfer_authorizations_are_spent_and_go_stale() {
+        use p256::ecdsa::signature::Signer;
+        use crate::proofs::UcwidKey;
+        use crate::transfer::{TransferAuthorization, TransferSigning, TRANSFER_AUTHORIZATION_WINDOW};
+        //
+        let clock = MockClock::default();
+        let mut tables = LocalSessionTokens::new(InMemoryDB::new(), None);
+        tables.set_clock(Some(Box::new(clock.clone())));
+        tables.set_transfer_signing(TransferSigning::Yielder);
+        let yielder_signer = p256::ecdsa::SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let receiver_signer = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+        let yielder = UcwidKey::P256(*yielder_signer.verifying_key()).to_ucwid();
+        let receiver = UcwidKey::Ed25519(receiver_signer.verifying_key()).to_ucwid();
+        tables.add_session(&"user+abc".to_string(), &yielder, None, None).await.unwrap();
+        tables.add_session(&"user+xyz".to_string(), &receiver, None, None).await.unwrap();
+        let t_token = "order+abc".to_string();
+        tables.add_transferable_token(&t_token, StructOrString::TypeStr("{}".to_string()), &yielder).await.unwrap();
+        //
+        let mut there = TransferAuthorization::new(&t_token, &yielder, &receiver, clock.now());
+        let signature : p256::ecdsa::Signature = yielder_signer.sign(&there.signing_message());
+        there._yielder_signature = signature.to_bytes().to_vec();
+        tables.transfer_token_signed(there.clone()).await.unwrap();
+        let mut back = TransferAuthorization::new(&t_token, &receiver, &yielder, clock.now());
+        back._yielder_signature = receiver_signer.sign(&back.signing_message()).to_bytes().to_vec();
+        tables.transfer_token_signed(back).await.unwrap();
+        //
+        there._yielder_signature = signature.to_der().as_bytes().to_vec();     // the same authorization, encoded differently
+        assert_eq!(tables.transfer_token_signed(there).await, Err(SessionTokenError::Replayed(t_token.clone())));
+        assert_eq!(tables.from_token(t_token.clone()), Ok(yielder.clone()));
+        //
+        let mut stale = TransferAuthorization::new(&t_token, &yielder, &receiver, clock.now());
+        let signature : p256::ecdsa::Signature = yielder_signer.sign(&stale.signing_message());
+        stale._yielder_signature = signature.to_bytes().to_vec();
+        clock.advance(TRANSFER_AUTHORIZATION_WINDOW as u64);
+        assert_eq!(tables.transfer_token_signed(stale).await, Err(SessionTokenError::Expired(t_token.clone())));
+    }
+}
@@ -0,0 +1,224 @@
+//
+//
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{now_millis, Millis};
+use crate::errors::SessionTokenResult;
+use crate::set_from_stored;
+
+
+// ---- ----
+
+pub const MINUTES : i32 = 1000*60;
+pub const GENERAL_DEFAULT_SESSION_TIMEOUT : i32 = 60*MINUTES;
+
+
+// ---- ----
+
+// deadline
+// an instant plus an allotted duration -- a negative duration has already run out
+
+fn deadline(from : Millis, allotted : i32) -> Millis {
+    from.saturating_add(allotted.max(0) as Millis)
+}
+
+// active_deadline
+// the absolute deadline, brought in by the idle allotment when there is one -- idle time counts from the last touch
+
+fn active_deadline(started_at : Millis, allotted : i32, last_touched : Millis, idle_allotted : Option<i32>) -> Millis {
+    let absolute = deadline(started_at,allotted);
+    match idle_allotted {
+        Some(idle) => absolute.min(deadline(started_at.max(last_touched),idle)),
+        _ => absolute
+    }
+}
+
+fn time_left(until : Millis, now : Millis) -> i32 {
+    let left = until.saturating_sub(now);
+    if left > (i32::MAX as Millis) { i32::MAX } else { left as i32 }
+}
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+/**
+ * There are several situations in which a sesion may be used in a time-sensitive way.
+ * This class puts them in a single record for storage in a sinlge local table.
+ * Many times, a token's timing roles may be manipulated at once. Hence, keeping the token in a table for each case
+ * will increase the algorithmic time a token's updates will require.
+ *
+ * Expiry is kept as instants plus allotted durations. The time left is computed from the clock when asked for,
+ * so it does not depend on how often expired sessions are removed.
+*/
+
+#[derive(Clone)]
+#[derive(Builder)]
+#[derive(Serialize, Deserialize)]
+pub struct SessionTimingInfo {
+    #[builder(default = "false")]
+    pub _detachment_allowed : bool,
+    #[builder(default = "false")]
+    pub _is_detached : bool,  // a session is detached when its owner has logged out but returning is allowed
+    #[builder(default = "now_millis()")]
+    pub _started_at : Millis,       // when the session was made or its timeout was last set
+    #[builder(default = "GENERAL_DEFAULT_SESSION_TIMEOUT")]
+    pub _time_allotted : i32,
+    #[builder(default = "0")]
+    pub _detached_at : Millis,
+    #[builder(default = "GENERAL_DEFAULT_SESSION_TIMEOUT")]
+    pub _time_allotted_after_detachment : i32,
+    #[builder(default = "false")]
+    pub _shared : bool,
+    #[builder(default = "0")]
+    pub _last_touched : Millis,     // when the session was last used -- the idle allotment counts from here
+    #[builder(default = "None")]
+    pub _idle_allotted : Option<i32>,
+ }
+
+impl SessionTimingInfo {
+    //
+    pub fn set_all(&mut self, stored_info : serde_json::Value) -> SessionTokenResult<()> {
+        set_from_stored(&mut self._detachment_allowed, &stored_info["_detachment_allowed"])?;
+        set_from_stored(&mut self._is_detached, &stored_info["_is_detached"])?;
+        set_from_stored(&mut self._started_at, &stored_info["_started_at"])?;
+        set_from_stored(&mut self._time_allotted, &stored_info["_time_allotted"])?;
+        set_from_stored(&mut self._detached_at, &stored_info["_detached_at"])?;
+        set_from_stored(&mut self._time_allotted_after_detachment, &stored_info["_time_allotted_after_detachment"])?;
+        set_from_stored(&mut self._shared, &stored_info["_shared"])?;
+        set_from_stored(&mut self._last_touched, &stored_info["_last_touched"])?;
+        set_from_stored(&mut self._idle_allotted, &stored_info["_idle_allotted"])?;
+        Ok(())
+    }
+
+    /// The instant at which the record runs out; the detachment allotment applies once detached.
+    /// While attached, the idle allotment (if any) may run out before the absolute one.
+    pub fn expires_at(&self) -> Millis {
+        if self._is_detached {
+            deadline(self._detached_at,self._time_allotted_after_detachment)
+        } else {
+            active_deadline(self._started_at,self._time_allotted,self._last_touched,self._idle_allotted)
+        }
+    }
+
+    pub fn time_left(&self, now : Millis) -> i32 {
+        time_left(self.expires_at(),now)
+    }
+
+    pub fn is_expired(&self, now : Millis) -> bool {
+        now >= self.expires_at()
+    }
+
+    /// Starts the allotted time over from `now`.
+    pub fn restart(&mut self, now : Millis, allotted : i32) {
+        self._started_at = now;
+        self._time_allotted = allotted;
+    }
+
+    /// Marks the record as used at `now`, which starts the idle allotment over.
+    pub fn touch(&mut self, now : Millis) {
+        self._last_touched = now;
+    }
+
+    pub fn detach(&mut self, now : Millis) {
+        self._is_detached = true;
+        self._detached_at = now;
+    }
+
+    pub fn attach(&mut self) {
+        self._is_detached = false;
+    }
+}
+
+
+
+/**
+ * There are several situations in which a token may be used in a time-sensitive way.
+ * This class puts them in a single record for storage in a sinlge local table.
+ * Many times, a token's timing roles may be manipulated at once. Hence, keeping the token in a table for each case
+ * will increase the algorithmic time a token's updates will require.
+ */
+#[derive(Clone)]
+#[derive(Builder)]
+pub struct TokenTimingInfo {
+    #[builder(default = "false")]
+    pub _detachment_allowed : bool,
+    #[builder(default = "false")]
+    pub _is_detached : bool,  // a token is detached when its session has ended and it waits for a new owner
+    #[builder(default = "now_millis()")]
+    pub _started_at : Millis,       // when the token was added or its timeout was last set
+    #[builder(default = "GENERAL_DEFAULT_SESSION_TIMEOUT")]
+    pub _time_allotted : i32,
+    #[builder(default = "0")]
+    pub _detached_at : Millis,      // when the token was disowned
+    #[builder(default = "GENERAL_DEFAULT_SESSION_TIMEOUT")]
+    pub _time_allotted_after_detachment : i32,
+    #[builder(default = "0")]
+    pub _last_touched : Millis,
+    #[builder(default = "None")]
+    pub _idle_allotted : Option<i32>,
+    #[builder(default = "None")]
+    pub _uses_left : Option<u32>,   // None for a token that may be used any number of times
+    #[builder(default = "0")]
+    pub _not_before : Millis,       // the token may not be used before this instant
+ }
+
+impl TokenTimingInfo {
+    //
+    pub fn set_all(&mut self, stored_info : serde_json::Value) -> SessionTokenResult<()> {
+        set_from_stored(&mut self._detachment_allowed, &stored_info["_detachment_allowed"])?;
+        set_from_stored(&mut self._is_detached, &stored_info["_is_detached"])?;
+        set_from_stored(&mut self._started_at, &stored_info["_started_at"])?;
+        set_from_stored(&mut self._time_allotted, &stored_info["_time_allotted"])?;
+        set_from_stored(&mut self._detached_at, &stored_info["_detached_at"])?;
+        set_from_stored(&mut self._time_allotted_after_detachment, &stored_info["_time_allotted_after_detachment"])?;
+        set_from_stored(&mut self._last_touched, &stored_info["_last_touched"])?;
+        set_from_stored(&mut self._idle_allotted, &stored_info["_idle_allotted"])?;
+        set_from_stored(&mut self._uses_left, &stored_info["_uses_left"])?;
+        set_from_stored(&mut self._not_before, &stored_info["_not_before"])?;
+        Ok(())
+    }
+
+    /// The instant at which the record runs out; the detachment allotment applies once detached.
+    /// While attached, the idle allotment (if any) may run out before the absolute one.
+    pub fn expires_at(&self) -> Millis {
+        if self._is_detached {
+            deadline(self._detached_at,self._time_allotted_after_detachment)
+        } else {
+            active_deadline(self._started_at,self._time_allotted,self._last_touched,self._idle_allotted)
+        }
+    }
+
+    pub fn time_left(&self, now : Millis) -> i32 {
+        time_left(self.expires_at(),now)
+    }
+
+    pub fn is_expired(&self, now : Millis) -> bool {
+        now >= self.expires_at()
+    }
+
+    /// Starts the allotted time over from `now`.
+    pub fn restart(&mut self, now : Millis, allotted : i32) {
+        self._started_at = now;
+        self._time_allotted = allotted;
+    }
+
+    /// Marks the record as used at `now`, which starts the idle allotment over.
+    pub fn touch(&mut self, now : Millis) {
+        self._last_touched = now;
+    }
+
+    pub fn is_pending(&self, now : Millis) -> bool {
+        now < self._not_before
+    }
+
+    pub fn detach(&mut self, now : Millis) {
+        self._is_detached = true;
+        self._detached_at = now;
+    }
+
+    pub fn attach(&mut self) {
+        self._is_detached = false;
+    }
+}
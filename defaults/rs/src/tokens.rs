@@ -0,0 +1,208 @@
+//
+//
+use async_trait::async_trait;
+use data_encoding::{BASE32_NOPAD, BASE64URL_NOPAD, HEXLOWER};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+pub type Hash = String;
+pub type SessionToken = String;
+pub type TransitionToken = String;
+pub type Ucwid = String;
+
+#[derive(Debug)]
+#[derive(Hash)]
+#[derive(Eq, PartialEq)]
+pub enum Token {
+    SessionToken(SessionToken),
+    TransitionToken(TransitionToken)
+}
+
+pub enum StructOrString<T> {
+    TypeStr(String),
+    TypeGen(T),
+}
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+/**
+ * Makes tokens for LocalSessionTokens, with an optional prefix.
+ * Any `Fn(Option<&str>) -> Token` that is Send and Sync is a generator, so a closure may capture
+ * the state it needs (a node id, a counter, a TokenFormat). Structs may carry configuration
+ * and implement the trait directly.
+ */
+pub trait TokenGenerator: Send + Sync {
+    fn generate(&self, prefix : Option<&str>) -> Token;
+}
+
+impl<F> TokenGenerator for F where F: Fn(Option<&str>) -> Token + Send + Sync {
+    fn generate(&self, prefix : Option<&str>) -> Token {
+        self(prefix)
+    }
+}
+
+/**
+ * A generator that has to wait on something, e.g. key material or an ID service, to make a token.
+ */
+#[async_trait]
+pub trait AsyncTokenGenerator: Send + Sync {
+    async fn generate(&self, prefix : Option<&str>) -> Token;
+}
+
+#[allow(non_camel_case_types)]
+pub type token_lambda = Box<dyn TokenGenerator>;
+
+#[allow(non_camel_case_types)]
+pub type async_token_lambda = Box<dyn AsyncTokenGenerator>;
+
+// ---- ----
+
+pub const SESSION_PEFIX : &str = "user+";
+
+
+// ---- ----
+
+
+/**
+ * The text form of the random part of a token.
+ * All three are safe to use in URLs and cookies; base64url is the shortest and hex is the default.
+ */
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+pub enum TokenEncoding {
+    Hex,
+    Base64Url,
+    Base32,
+}
+
+/**
+ * How much randomness goes into a token and how it is written out.
+ * The bytes are drawn from the operating system's CSPRNG; 16 bytes gives 128 bits of entropy.
+ */
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+pub struct TokenFormat {
+    pub _entropy_bytes : usize,
+    pub _encoding : TokenEncoding,
+}
+
+pub const DEFAULT_ENTROPY_BYTES : usize = 16;
+pub const DEFAULT_TOKEN_FORMAT : TokenFormat = TokenFormat { _entropy_bytes : DEFAULT_ENTROPY_BYTES, _encoding : TokenEncoding::Hex };
+
+impl Default for TokenFormat {
+    fn default() -> Self {
+        DEFAULT_TOKEN_FORMAT
+    }
+}
+
+
+// gen_random_str
+// fill the requested number of bytes from the OS CSPRNG and encode them
+
+fn gen_random_str(format : &TokenFormat) -> String {
+    let mut bytes = vec![0u8; format._entropy_bytes];
+    OsRng.fill_bytes(&mut bytes);
+    match format._encoding {
+        TokenEncoding::Hex => HEXLOWER.encode(&bytes),
+        TokenEncoding::Base64Url => BASE64URL_NOPAD.encode(&bytes),
+        TokenEncoding::Base32 => BASE32_NOPAD.encode(&bytes),
+    }
+}
+
+/**
+ * Make a token with the given format.
+ * A token made with the session prefix is a SessionToken, all others are TransitionTokens.
+ * Applications wanting a different format may use a FormattedTokenMaker as their token_lambda.
+ */
+pub fn make_token(prefix : Option<&str>, format : &TokenFormat) -> Token {
+    //
+    let rstr : String = gen_random_str(format);
+    //
+    let token : Token;
+    match prefix {
+        Some(prfx) => {
+            let stoken : String = prfx.to_owned() + rstr.as_str();
+            if prfx == SESSION_PEFIX {
+                token = Token::SessionToken(stoken);
+            } else {
+                token = Token::TransitionToken(stoken);
+            }
+        },
+        None => {
+            token = Token::TransitionToken(rstr);
+        }
+    };
+    token
+}
+
+/**
+ * A default token producer is available for implementations that omit
+ * an application defined token producer
+ */
+pub fn default_token_maker(prefix : Option<&str>) -> Token {
+    make_token(prefix, &DEFAULT_TOKEN_FORMAT)
+}
+
+
+/**
+ * A token generator configured with its own TokenFormat.
+ */
+#[derive(Clone)]
+pub struct FormattedTokenMaker {
+    pub _format : TokenFormat,
+}
+
+impl FormattedTokenMaker {
+    pub fn new(format : TokenFormat) -> FormattedTokenMaker {
+        FormattedTokenMaker { _format : format }
+    }
+}
+
+impl TokenGenerator for FormattedTokenMaker {
+    fn generate(&self, prefix : Option<&str>) -> Token {
+        make_token(prefix, &self._format)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_str(token : Token) -> String {
+        match token {
+            Token::SessionToken(st) => st,
+            Token::TransitionToken(tt) => tt,
+        }
+    }
+
+    #[test]
+    fn encodings_carry_the_requested_entropy() {
+        let hex = TokenFormat { _entropy_bytes : 16, _encoding : TokenEncoding::Hex };
+        let b64 = TokenFormat { _entropy_bytes : 32, _encoding : TokenEncoding::Base64Url };
+        let b32 = TokenFormat { _entropy_bytes : 20, _encoding : TokenEncoding::Base32 };
+        assert_eq!(token_str(make_token(None, &hex)).len(), 32);
+        assert_eq!(token_str(make_token(None, &b64)).len(), 43);
+        assert_eq!(token_str(make_token(None, &b32)).len(), 32);
+        assert_ne!(default_token_maker(None), default_token_maker(None));
+    }
+
+    #[test]
+    fn session_prefix_makes_session_tokens() {
+        match default_token_maker(Some(SESSION_PEFIX)) {
+            Token::SessionToken(st) => assert!(st.starts_with(SESSION_PEFIX)),
+            _ => panic!("expected a session token")
+        }
+        match default_token_maker(Some("media+")) {
+            Token::TransitionToken(tt) => assert!(tt.starts_with("media+")),
+            _ => panic!("expected a transition token")
+        }
+    }
+}
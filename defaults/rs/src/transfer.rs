@@ -0,0 +1,155 @@
+//
+//
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Millis;
+use crate::errors::{SessionTokenError, SessionTokenResult};
+use crate::proofs::UcwidKey;
+use crate::set_from_stored;
+use crate::timing::MINUTES;
+use crate::tokens::{TransitionToken, Ucwid};
+
+
+// ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ---- ----
+
+
+/**
+ * Transferable tokens may be moved to another owner at any time a session owner allows it.
+ * By virue of being in a set of transferable tokens, the token is tansferable.
+ * The token may additionally be sellable at some price, which may positive or negative.
+ * Other useful properties may be added later that apply only to transferable tokens.
+ */
+#[derive(Clone)]
+#[derive(Builder)]
+pub struct TransferableTokenInfo {
+    #[builder(default = "false")]
+    pub _sellable : bool,
+    #[builder(default = "0.0")]
+    pub _price: f32,
+    pub _owner : Ucwid,
+}
+
+impl TransferableTokenInfo {
+    //
+    pub fn set_all(&mut self, stored_info : serde_json::Value) -> SessionTokenResult<()> {
+        set_from_stored(&mut self._sellable, &stored_info["_sellable"])?;
+        set_from_stored(&mut self._price, &stored_info["_price"])?;
+        match &stored_info["_owner"] {
+            serde_json::Value::Null => (),
+            serde_json::Value::String(owner) => {
+                self._owner = owner.to_string();
+            }
+            _ => {
+                return Err(SessionTokenError::Serialization("_owner is not a string".to_string()))
+            }
+        }
+        Ok(())
+    }
+}
+
+
+
+// how long after it is issued a transfer authorization may be used
+pub const TRANSFER_AUTHORIZATION_WINDOW : i32 = 10*MINUTES;
+
+
+/**
+ * How much a transfer_token call has to show before a token changes owner.
+ * With signing, the transfer has to be made with transfer_token_signed and an authorization
+ * signed by the key the yielder's Ucwid wraps (and, when acceptance is required, the receiver's too).
+ */
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+#[derive(Default)]
+pub enum TransferSigning {
+    #[default]
+    Unsigned,
+    Yielder,
+    YielderAndReceiver,
+}
+
+
+/**
+ * The yielder's signed word that a token may go to the receiver, kept by the tables as proof of the transfer.
+ * The yielder signs `signing_message`; a receiver accepting the token signs `acceptance_message`.
+ * The issue time makes each authorization distinct, so that one already used is refused,
+ * and an authorization is only good for TRANSFER_AUTHORIZATION_WINDOW after it.
+ */
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Eq, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct TransferAuthorization {
+    pub _token : TransitionToken,
+    pub _yielder : Ucwid,
+    pub _receiver : Ucwid,
+    pub _issued_at : Millis,
+    pub _yielder_signature : Vec<u8>,
+    pub _receiver_signature : Option<Vec<u8>>,
+}
+
+impl TransferAuthorization {
+    //
+    pub fn new(token : &TransitionToken, yielder : &Ucwid, receiver : &Ucwid, issued_at : Millis) -> TransferAuthorization {
+        TransferAuthorization {
+            _token : token.to_string(),
+            _yielder : yielder.to_string(),
+            _receiver : receiver.to_string(),
+            _issued_at : issued_at,
+            _yielder_signature : vec![],
+            _receiver_signature : None,
+        }
+    }
+
+    pub fn signing_message(&self) -> Vec<u8> {
+        format!("transfer.{}.{}.{}.{}", self._token, self._yielder, self._receiver, self._issued_at).into_bytes()
+    }
+
+    pub fn acceptance_message(&self) -> Vec<u8> {
+        format!("accept.{}.{}.{}.{}", self._token, self._yielder, self._receiver, self._issued_at).into_bytes()
+    }
+
+    // verify
+    // the yielder's signature has to verify; the receiver's has to be there when acceptance is required,
+    // and has to verify whenever it is there
+
+    pub fn verify(&self, signing : TransferSigning) -> SessionTokenResult<()> {
+        let signed_by = |ucwid : &Ucwid, message : &[u8], signature : &[u8]| {
+            UcwidKey::from_ucwid(ucwid).is_some_and(|key| key.verify(message,signature))
+        };
+        if !signed_by(&self._yielder,&self.signing_message(),&self._yielder_signature) {
+            return Err(SessionTokenError::Forged(self._token.to_string()))
+        }
+        match &self._receiver_signature {
+            Some(signature) => {
+                if !signed_by(&self._receiver,&self.acceptance_message(),signature) {
+                    return Err(SessionTokenError::Forged(self._token.to_string()))
+                }
+            }
+            _ => {
+                if signing == TransferSigning::YielderAndReceiver {
+                    return Err(SessionTokenError::Unauthorized(self._token.to_string()))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_info_with_a_bad_owner_is_an_error() {
+        let mut info = TransferableTokenInfoBuilder::default()._owner("owner-1".to_string()).build().unwrap();
+        let stored = serde_json::json!({ "_sellable" : true, "_owner" : 5 });
+        assert_eq!(info.set_all(stored), Err(SessionTokenError::Serialization("_owner is not a string".to_string())));
+        info.set_all(serde_json::json!({ "_owner" : "owner-2" })).unwrap();
+        assert_eq!(info._owner, "owner-2".to_string());
+    }
+}